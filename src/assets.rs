@@ -1,3 +1,7 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
 use base64;
 use lazy_static::lazy_static;
 
@@ -7,3 +11,104 @@ lazy_static! {
     pub static ref ON_PLAYER_LEFT_ROOM_ICON: String =
         base64::encode(include_bytes!("assets/left.png"));
 }
+
+// Reads an image file, e.g. a PNG, as the base64 string which XSOverlay takes as an icon.
+pub fn load_icon(path: &Path) -> io::Result<String> {
+    fs::read(path).map(base64::encode)
+}
+
+// The icon files which replace the embedded ones. None keeps the embedded one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IconPaths {
+    pub joined: Option<PathBuf>,
+    pub left: Option<PathBuf>,
+    // Replaces the warning icon of XSOverlay.
+    pub warning: Option<PathBuf>,
+}
+
+// The icons encoded once, so that the files are not read for every notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconSet {
+    pub joined: String,
+    pub left: String,
+    // None shows the warning icon of XSOverlay.
+    pub warning: Option<String>,
+}
+
+impl Default for IconSet {
+    fn default() -> Self {
+        IconSet {
+            joined: ON_PLAYER_JOINED_ROOM_ICON.clone(),
+            left: ON_PLAYER_LEFT_ROOM_ICON.clone(),
+            warning: None,
+        }
+    }
+}
+
+impl IconSet {
+    // An icon which can't be read, e.g. a missing file, falls back to the embedded one.
+    pub fn load(paths: &IconPaths) -> IconSet {
+        let defaults = IconSet::default();
+        IconSet {
+            joined: load_or(&paths.joined, defaults.joined),
+            left: load_or(&paths.left, defaults.left),
+            warning: paths.warning.as_deref().and_then(|path| {
+                load_icon(path)
+                    .map_err(|e| report_icon_error(path, &e))
+                    .ok()
+            }),
+        }
+    }
+}
+
+fn load_or(path: &Option<PathBuf>, default: String) -> String {
+    match path {
+        Some(path) => load_icon(path).unwrap_or_else(|e| {
+            report_icon_error(path, &e);
+            default
+        }),
+        None => default,
+    }
+}
+
+fn report_icon_error(path: &Path, e: &io::Error) {
+    eprintln!(
+        "Failed to load the icon {}, using the default one: {}",
+        path.display(),
+        e
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icon_set_loads_icon_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("joined.png");
+        let png = b"\x89PNG\r\n\x1a\n";
+        fs::write(&path, png).unwrap();
+        let icons = IconSet::load(&IconPaths {
+            joined: Some(path.clone()),
+            warning: Some(path),
+            ..Default::default()
+        });
+        assert_eq!(base64::encode(png), icons.joined);
+        assert_eq!(Some(base64::encode(png)), icons.warning);
+        assert_eq!(*ON_PLAYER_LEFT_ROOM_ICON, icons.left);
+    }
+
+    #[test]
+    fn icon_set_falls_back_to_embedded_icons() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.png");
+        let icons = IconSet::load(&IconPaths {
+            joined: Some(missing.clone()),
+            left: None,
+            warning: Some(missing),
+        });
+        assert_eq!(IconSet::default(), icons);
+        assert_eq!(IconSet::default(), IconSet::load(&IconPaths::default()));
+    }
+}
@@ -0,0 +1,1121 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::NaiveTime;
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Deserializer};
+use vrc_doorkeeper::assets::IconPaths;
+use vrc_doorkeeper::notifier::{Anonymization, EventTimeouts, QuietHours};
+use vrc_doorkeeper::vrc::log::{AmbiguousTimePolicy, CustomRule};
+
+// Read from the directory of the executable if it exists and --config is not given.
+const DEFAULT_CONFIG_FILE_NAME: &str = "vrc_doorkeeper.toml";
+
+/// Notifies joins and leaves of VRChat players to XSOverlay.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Args {
+    /// TOML file to read the settings from. The arguments take precedence over it.
+    /// Defaults to `vrc_doorkeeper.toml` next to the executable, if any.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Interval between reads of the log file, in milliseconds. Defaults to 1000.
+    #[arg(long, value_name = "MILLISECONDS",
+        value_parser = clap::value_parser!(u64).range(1..))]
+    poll_interval: Option<u64>,
+
+    /// Read at most this many lines of the log in each poll, and the rest in the next ones.
+    #[arg(long, value_name = "LINES",
+        value_parser = clap::value_parser!(u64).range(1..))]
+    max_lines_per_poll: Option<u64>,
+
+    /// Overlay which notifications are sent to. Defaults to xsoverlay.
+    #[arg(long, value_enum)]
+    overlay: Option<Overlay>,
+
+    /// Host which XSOverlay listens on. Defaults to 127.0.0.1.
+    #[arg(long, value_name = "HOST")]
+    xsoverlay_host: Option<String>,
+
+    /// Local address to send notifications from, e.g. `0.0.0.0:0` for XSOverlay on another machine.
+    /// Defaults to 127.0.0.1:0.
+    #[arg(long, value_name = "ADDR")]
+    xsoverlay_bind: Option<String>,
+
+    /// Port which XSOverlay listens on. Defaults to XSOVERLAY_PORT, or 42069.
+    #[arg(long, value_name = "PORT",
+        value_parser = clap::value_parser!(u16).range(1..))]
+    xsoverlay_port: Option<u16>,
+
+    /// Show notifications on the desktop while XSOverlay is not reachable on its WebSocket API port.
+    #[arg(long)]
+    desktop_fallback: bool,
+
+    /// Also write joins and leaves into the VRChat chatbox via OSC, which others can read.
+    #[arg(long)]
+    osc_chatbox: bool,
+
+    /// Warn when the instance is full.
+    #[arg(long)]
+    notify_instance_full: bool,
+
+    /// Do not send the notification on startup.
+    #[arg(long)]
+    no_welcome: bool,
+
+    /// Content of the notification sent on startup.
+    #[arg(long, value_name = "TEXT")]
+    welcome_message: Option<String>,
+
+    /// Give the notifications this many indices in turn from 0, so that rapid ones are stacked
+    /// instead of replacing each other. 1 gives them all the index 0. Defaults to 10.
+    #[arg(long, value_name = "COUNT",
+        value_parser = clap::value_parser!(i32).range(1..))]
+    index_limit: Option<i32>,
+
+    /// Name which the notifications are grouped by in the overlay.
+    #[arg(long, value_name = "NAME")]
+    source_app: Option<String>,
+
+    /// Listen for `mute`, `unmute` and `toggle` commands on this localhost UDP port.
+    #[arg(long, value_name = "PORT",
+        value_parser = clap::value_parser!(u16).range(1..))]
+    control_port: Option<u16>,
+
+    /// Serve the counters in the Prometheus text format at `/metrics` on this address,
+    /// e.g. `127.0.0.1:9100`.
+    #[arg(long, value_name = "ADDR")]
+    metrics_addr: Option<String>,
+
+    /// Also write every event as a line of JSON to this file, or to stdout if `-`.
+    #[arg(long, value_name = "PATH")]
+    json_lines: Option<PathBuf>,
+
+    /// Print notifications to stdout instead of sending them.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Read the log once and exit, with a failure status if it could not be read.
+    #[arg(long)]
+    once: bool,
+
+    /// Read the log from stdin until its end instead of the log directory.
+    #[arg(long)]
+    stdin: bool,
+
+    /// Print the log entries in which no event was found, and count the lines without the header.
+    #[arg(long)]
+    trace_unmatched: bool,
+
+    /// Replay this log as far apart as its lines were logged and exit, e.g. for a demo.
+    /// Takes precedence over --stdin and --once.
+    #[arg(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+
+    /// How many times as fast as logged to replay. Defaults to 1.
+    #[arg(long, value_name = "MULTIPLIER", value_parser = positive_speed)]
+    speed: Option<f64>,
+
+    /// Notify friends coming online or going offline.
+    #[arg(long)]
+    notify_friend_presence: bool,
+
+    /// Make notifications with long text taller so that they are not clipped.
+    #[arg(long)]
+    auto_height: bool,
+
+    /// Confirm that a screenshot was saved.
+    #[arg(long)]
+    notify_photo_taken: bool,
+
+    /// Warn when a portal is dropped in the instance.
+    #[arg(long)]
+    notify_portal_dropped: bool,
+
+    /// Notify the player who has become the instance master.
+    #[arg(long)]
+    notify_master_changed: bool,
+
+    /// Warn when the download of a world or an avatar stalls.
+    #[arg(long)]
+    notify_download_stalled: bool,
+
+    /// Confirm the account logged in to VRChat, including when reading starts.
+    #[arg(long)]
+    notify_login: bool,
+
+    /// With --notify-login, do not confirm the login already in the log when reading starts.
+    #[arg(long)]
+    no_login_on_first_read: bool,
+
+    /// Notify the world entered once it has been loaded.
+    #[arg(long)]
+    notify_world: bool,
+
+    /// Do not notify the players joining.
+    #[arg(long)]
+    no_joins: bool,
+
+    /// Do not notify the players leaving.
+    #[arg(long)]
+    no_leaves: bool,
+
+    /// Do not notify that VRChat was closed.
+    #[arg(long)]
+    no_quit: bool,
+
+    /// Do not warn of kicks, bans, timeouts and warnings.
+    #[arg(long)]
+    no_moderation: bool,
+
+    /// Prefix joins and leaves with the name of the current world.
+    #[arg(long)]
+    show_world_name: bool,
+
+    /// Hide the names of the players in the notifications, by their first character
+    /// or by a hash which is the same for the same name.
+    #[arg(long, value_enum, value_name = "MODE")]
+    anonymize_names: Option<AnonymizeNames>,
+
+    /// Append the local time of the event to the notifications, e.g. `(23:41)`.
+    #[arg(long)]
+    show_timestamp: bool,
+
+    /// chrono format of the time appended by --show-timestamp. Defaults to `%H:%M`.
+    #[arg(long, value_name = "FORMAT", value_parser = timestamp_format)]
+    timestamp_format: Option<String>,
+
+    /// Show how long the player was in the instance in leave notifications.
+    #[arg(long)]
+    show_time_in_instance: bool,
+
+    /// Summarize the session when leaving a world.
+    #[arg(long)]
+    session_summary: bool,
+
+    /// Warn when the number of players logged by VRChat differs from the players known.
+    #[arg(long)]
+    reconcile_roster: bool,
+
+    /// Notify entering the same instance again within this many seconds after leaving it.
+    #[arg(long, value_name = "SECONDS")]
+    rejoin_window: Option<u32>,
+
+    /// Warn of a disconnect when no world has been entered for this many seconds after leaving one.
+    #[arg(long, value_name = "SECONDS")]
+    disconnect_window: Option<u32>,
+
+    /// Notify a player who joined and left within this many seconds as passing through,
+    /// and the join only after it if the player stays.
+    #[arg(long, value_name = "SECONDS")]
+    pass_through_window: Option<u32>,
+
+    /// Do not notify the leaves logged within this many seconds before leaving the instance,
+    /// which VRChat logs while tearing it down. The leaves are notified after this delay.
+    #[arg(long, value_name = "SECONDS")]
+    teardown_window: Option<u32>,
+
+    /// Wait until no one has joined for this many milliseconds after entering a world,
+    /// instead of a fixed 5 seconds, before notifying joins and leaves.
+    #[arg(long, value_name = "MILLISECONDS")]
+    load_quiet_period: Option<u32>,
+
+    /// How to read log times which are ambiguous or skipped around a DST transition.
+    /// Defaults to earliest, which drops the skipped times.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    ambiguous_time: Option<AmbiguousTime>,
+
+    /// Warn once when no log has been found for this many seconds. Defaults to 60.
+    #[arg(long, value_name = "SECONDS")]
+    missing_log_grace: Option<u32>,
+
+    /// Confirm every this many minutes that the log is still watched, with the number of players.
+    #[arg(long, value_name = "MINUTES",
+        value_parser = clap::value_parser!(u32).range(1..))]
+    heartbeat_interval: Option<u32>,
+
+    /// Report every this many minutes the joins and leaves of the last minute, while any happened.
+    #[arg(long, value_name = "MINUTES",
+        value_parser = clap::value_parser!(u32).range(1..))]
+    rate_summary_interval: Option<u32>,
+
+    /// Directory containing the VRChat logs. Defaults to the VRChat directory under LocalLow.
+    #[arg(long, value_name = "DIR")]
+    log_dir: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overlay {
+    #[value(name = "xsoverlay")]
+    #[serde(rename = "xsoverlay")]
+    XsOverlay,
+    OvrToolkit,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AmbiguousTime {
+    Earliest,
+    Latest,
+    Skip,
+}
+
+impl From<AmbiguousTime> for AmbiguousTimePolicy {
+    fn from(value: AmbiguousTime) -> Self {
+        match value {
+            AmbiguousTime::Earliest => AmbiguousTimePolicy::Earliest,
+            AmbiguousTime::Latest => AmbiguousTimePolicy::Latest,
+            AmbiguousTime::Skip => AmbiguousTimePolicy::Skip,
+        }
+    }
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnonymizeNames {
+    Masked,
+    Hashed,
+}
+
+impl From<AnonymizeNames> for Anonymization {
+    fn from(value: AnonymizeNames) -> Self {
+        match value {
+            AnonymizeNames::Masked => Anonymization::Masked,
+            AnonymizeNames::Hashed => Anonymization::Hashed,
+        }
+    }
+}
+
+// The settings in the config file, named after the arguments.
+// A missing key keeps the default, as an argument not given does.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileConfig {
+    poll_interval: Option<u64>,
+    max_lines_per_poll: Option<u64>,
+    overlay: Option<Overlay>,
+    xsoverlay_host: Option<String>,
+    xsoverlay_bind: Option<String>,
+    xsoverlay_port: Option<u16>,
+    desktop_fallback: bool,
+    log_dir: Option<PathBuf>,
+    ambiguous_time: Option<AmbiguousTime>,
+    osc_chatbox: bool,
+    notify_instance_full: bool,
+    notify_friend_presence: bool,
+    notify_photo_taken: bool,
+    notify_portal_dropped: bool,
+    notify_master_changed: bool,
+    notify_download_stalled: bool,
+    notify_login: bool,
+    no_login_on_first_read: bool,
+    notify_world: bool,
+    no_joins: bool,
+    no_leaves: bool,
+    no_quit: bool,
+    no_moderation: bool,
+    show_world_name: bool,
+    anonymize_names: Option<AnonymizeNames>,
+    show_timestamp: bool,
+    #[serde(deserialize_with = "deserialize_timestamp_format")]
+    timestamp_format: Option<String>,
+    show_time_in_instance: bool,
+    session_summary: bool,
+    reconcile_roster: bool,
+    auto_height: bool,
+    rejoin_window: Option<u32>,
+    pass_through_window: Option<u32>,
+    teardown_window: Option<u32>,
+    disconnect_window: Option<u32>,
+    load_quiet_period: Option<u32>,
+    missing_log_grace: Option<u32>,
+    heartbeat_interval: Option<u32>,
+    rate_summary_interval: Option<u32>,
+    // In seconds, as the other windows.
+    batch_window: Option<u32>,
+    flap_window: Option<u32>,
+    quiet_hours: Option<QuietHoursConfig>,
+    timeouts: TimeoutsConfig,
+    icons: IconsConfig,
+    audio: AudioConfig,
+    custom_rules: Vec<CustomRuleConfig>,
+    no_welcome: bool,
+    welcome_message: Option<String>,
+    index_limit: Option<i32>,
+    source_app: Option<String>,
+    control_port: Option<u16>,
+    metrics_addr: Option<String>,
+    json_lines: Option<PathBuf>,
+    dry_run: bool,
+}
+
+// e.g. `quiet-hours = { start = "23:00", end = "07:00" }`
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct QuietHoursConfig {
+    #[serde(deserialize_with = "deserialize_time")]
+    start: NaiveTime,
+    #[serde(deserialize_with = "deserialize_time")]
+    end: NaiveTime,
+}
+
+// In seconds, e.g. `timeouts = { presence = 1.5, warning = 5 }`
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct TimeoutsConfig {
+    presence: Option<f32>,
+    info: Option<f32>,
+    warning: Option<f32>,
+}
+
+// e.g. `icons = { joined = "joined.png", warning = "warning.png" }`
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct IconsConfig {
+    joined: Option<PathBuf>,
+    left: Option<PathBuf>,
+    warning: Option<PathBuf>,
+}
+
+// The sound files of joins and leaves, e.g. `audio = { joined = "joined.wav" }`
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+struct AudioConfig {
+    joined: Option<String>,
+    left: Option<String>,
+}
+
+// e.g.
+// [[custom-rules]]
+// name = "dice"
+// pattern = '^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)$'
+// template = "{user} rolled {number}."
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CustomRuleConfig {
+    pub name: String,
+    pub pattern: String,
+    pub template: String,
+}
+
+impl CustomRuleConfig {
+    pub fn compile(&self) -> Result<CustomRule, regex::Error> {
+        CustomRule::new(
+            self.name.to_owned(),
+            &self.pattern,
+            self.template.to_owned(),
+        )
+    }
+}
+
+pub fn compile_custom_rules(rules: &[CustomRuleConfig]) -> Result<Vec<CustomRule>, regex::Error> {
+    rules.iter().map(CustomRuleConfig::compile).collect()
+}
+
+fn positive_speed(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        Ok(_) => Err("must be a positive number".to_owned()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Rejects a format which chrono can't render, instead of failing when the first event is notified.
+fn timestamp_format(text: &str) -> Result<String, String> {
+    if StrftimeItems::new(text).any(|item| item == Item::Error) {
+        return Err(format!("invalid format: {}", text));
+    }
+    Ok(text.to_owned())
+}
+
+fn deserialize_timestamp_format<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    timestamp_format(&text)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_time<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let text = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&text, "%H:%M").map_err(serde::de::Error::custom)
+}
+
+impl FileConfig {
+    pub fn from_toml(text: &str) -> Result<FileConfig, toml::de::Error> {
+        toml::from_str(text)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadError(PathBuf, io::Error),
+    ParseError(PathBuf, Box<toml::de::Error>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ReadError(path, e) => {
+                write!(f, "failed to read {}: {}", path.display(), e)
+            }
+            ConfigError::ParseError(path, e) => {
+                write!(f, "invalid config in {}: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub poll_interval: Duration,
+    pub max_lines_per_poll: Option<usize>,
+    pub overlay: Overlay,
+    pub xsoverlay_host: String,
+    // None reads the port from XSOVERLAY_PORT.
+    pub xsoverlay_port: Option<u16>,
+    pub xsoverlay_bind: String,
+    pub desktop_fallback: bool,
+    pub log_dir: Option<PathBuf>,
+    pub ambiguous_time: AmbiguousTimePolicy,
+    pub osc_chatbox: bool,
+    pub notify_instance_full: bool,
+    pub notify_friend_presence: bool,
+    pub notify_photo_taken: bool,
+    pub notify_portal_dropped: bool,
+    pub notify_master_changed: bool,
+    pub notify_download_stalled: bool,
+    pub notify_login: bool,
+    pub no_login_on_first_read: bool,
+    pub notify_world: bool,
+    pub no_joins: bool,
+    pub no_leaves: bool,
+    pub no_quit: bool,
+    pub no_moderation: bool,
+    pub session_summary: bool,
+    pub reconcile_roster: bool,
+    pub show_world_name: bool,
+    pub anonymize_names: Option<Anonymization>,
+    pub show_timestamp: bool,
+    pub timestamp_format: Option<String>,
+    pub show_time_in_instance: bool,
+    pub auto_height: bool,
+    pub rejoin_window: Option<Duration>,
+    pub pass_through_window: Option<Duration>,
+    pub teardown_window: Option<Duration>,
+    pub disconnect_window: Option<Duration>,
+    pub load_quiet_period: Option<Duration>,
+    pub missing_log_grace: Duration,
+    pub heartbeat_interval: Option<Duration>,
+    pub rate_summary_interval: Option<Duration>,
+    pub batch_window: Option<Duration>,
+    pub flap_window: Option<Duration>,
+    pub quiet_hours: Option<QuietHours>,
+    // The timeout of joins and leaves, which the message defaults have.
+    pub presence_timeout: Option<f32>,
+    pub timeouts: EventTimeouts,
+    pub icons: IconPaths,
+    // None plays the default sound of the overlay.
+    pub join_audio: Option<String>,
+    pub leave_audio: Option<String>,
+    pub custom_rules: Vec<CustomRuleConfig>,
+    pub no_welcome: bool,
+    pub welcome_message: Option<String>,
+    pub index_limit: i32,
+    pub source_app: Option<String>,
+    pub control_port: Option<u16>,
+    pub metrics_addr: Option<String>,
+    pub json_lines: Option<PathBuf>,
+    pub dry_run: bool,
+    // The config file read, which is watched for the changes of the custom rules.
+    pub config_path: Option<PathBuf>,
+    pub once: bool,
+    pub stdin: bool,
+    pub trace_unmatched: bool,
+    pub replay: Option<PathBuf>,
+    pub speed: f64,
+}
+
+impl Config {
+    // Reads the config file given by the arguments, or the default one if it exists.
+    pub fn load(args: Args) -> Result<Config, ConfigError> {
+        let path = match &args.config {
+            Some(path) => Some(path.to_owned()),
+            None => std::env::current_exe()
+                .ok()
+                .map(|exe| exe.with_file_name(DEFAULT_CONFIG_FILE_NAME))
+                .filter(|path| path.is_file()),
+        };
+        let file = match &path {
+            Some(path) => read_file_config(path)?,
+            None => FileConfig::default(),
+        };
+        Ok(Config {
+            config_path: path,
+            ..Config::new(args, file)
+        })
+    }
+
+    pub fn new(args: Args, file: FileConfig) -> Config {
+        let seconds = |seconds: u32| Duration::from_secs(seconds.into());
+        Config {
+            poll_interval: Duration::from_millis(
+                args.poll_interval.or(file.poll_interval).unwrap_or(1000),
+            ),
+            max_lines_per_poll: args
+                .max_lines_per_poll
+                .or(file.max_lines_per_poll)
+                .map(|lines| lines as usize),
+            overlay: args.overlay.or(file.overlay).unwrap_or(Overlay::XsOverlay),
+            xsoverlay_host: args
+                .xsoverlay_host
+                .or(file.xsoverlay_host)
+                .unwrap_or_else(|| "127.0.0.1".to_owned()),
+            xsoverlay_port: args.xsoverlay_port.or(file.xsoverlay_port),
+            xsoverlay_bind: args
+                .xsoverlay_bind
+                .or(file.xsoverlay_bind)
+                .unwrap_or_else(|| "127.0.0.1:0".to_owned()),
+            desktop_fallback: args.desktop_fallback || file.desktop_fallback,
+            log_dir: args.log_dir.or(file.log_dir),
+            ambiguous_time: args
+                .ambiguous_time
+                .or(file.ambiguous_time)
+                .unwrap_or(AmbiguousTime::Earliest)
+                .into(),
+            osc_chatbox: args.osc_chatbox || file.osc_chatbox,
+            notify_instance_full: args.notify_instance_full || file.notify_instance_full,
+            notify_friend_presence: args.notify_friend_presence || file.notify_friend_presence,
+            notify_photo_taken: args.notify_photo_taken || file.notify_photo_taken,
+            notify_portal_dropped: args.notify_portal_dropped || file.notify_portal_dropped,
+            notify_master_changed: args.notify_master_changed || file.notify_master_changed,
+            notify_download_stalled: args.notify_download_stalled || file.notify_download_stalled,
+            notify_login: args.notify_login || file.notify_login,
+            no_login_on_first_read: args.no_login_on_first_read || file.no_login_on_first_read,
+            notify_world: args.notify_world || file.notify_world,
+            no_joins: args.no_joins || file.no_joins,
+            no_leaves: args.no_leaves || file.no_leaves,
+            no_quit: args.no_quit || file.no_quit,
+            no_moderation: args.no_moderation || file.no_moderation,
+            session_summary: args.session_summary || file.session_summary,
+            reconcile_roster: args.reconcile_roster || file.reconcile_roster,
+            show_world_name: args.show_world_name || file.show_world_name,
+            anonymize_names: args
+                .anonymize_names
+                .or(file.anonymize_names)
+                .map(Anonymization::from),
+            show_timestamp: args.show_timestamp || file.show_timestamp,
+            timestamp_format: args.timestamp_format.or(file.timestamp_format),
+            show_time_in_instance: args.show_time_in_instance || file.show_time_in_instance,
+            auto_height: args.auto_height || file.auto_height,
+            rejoin_window: args.rejoin_window.or(file.rejoin_window).map(seconds),
+            pass_through_window: args
+                .pass_through_window
+                .or(file.pass_through_window)
+                .map(seconds),
+            teardown_window: args.teardown_window.or(file.teardown_window).map(seconds),
+            disconnect_window: args
+                .disconnect_window
+                .or(file.disconnect_window)
+                .map(seconds),
+            load_quiet_period: args
+                .load_quiet_period
+                .or(file.load_quiet_period)
+                .map(|millis| Duration::from_millis(millis.into())),
+            missing_log_grace: seconds(
+                args.missing_log_grace
+                    .or(file.missing_log_grace)
+                    .unwrap_or(60),
+            ),
+            heartbeat_interval: args
+                .heartbeat_interval
+                .or(file.heartbeat_interval)
+                .map(|minutes| Duration::from_secs(u64::from(minutes) * 60)),
+            rate_summary_interval: args
+                .rate_summary_interval
+                .or(file.rate_summary_interval)
+                .map(|minutes| Duration::from_secs(u64::from(minutes) * 60)),
+            batch_window: file.batch_window.map(seconds),
+            flap_window: file.flap_window.map(seconds),
+            quiet_hours: file.quiet_hours.map(|hours| QuietHours {
+                start: hours.start,
+                end: hours.end,
+            }),
+            presence_timeout: file.timeouts.presence,
+            timeouts: EventTimeouts {
+                info: file.timeouts.info.unwrap_or(EventTimeouts::default().info),
+                warning: file
+                    .timeouts
+                    .warning
+                    .unwrap_or(EventTimeouts::default().warning),
+            },
+            icons: IconPaths {
+                joined: file.icons.joined,
+                left: file.icons.left,
+                warning: file.icons.warning,
+            },
+            join_audio: file.audio.joined,
+            leave_audio: file.audio.left,
+            custom_rules: file.custom_rules,
+            no_welcome: args.no_welcome || file.no_welcome,
+            welcome_message: args.welcome_message.or(file.welcome_message),
+            index_limit: args.index_limit.or(file.index_limit).unwrap_or(10),
+            source_app: args.source_app.or(file.source_app),
+            control_port: args.control_port.or(file.control_port),
+            metrics_addr: args.metrics_addr.or(file.metrics_addr),
+            json_lines: args.json_lines.or(file.json_lines),
+            dry_run: args.dry_run || file.dry_run,
+            config_path: None,
+            once: args.once,
+            stdin: args.stdin,
+            trace_unmatched: args.trace_unmatched,
+            replay: args.replay,
+            speed: args.speed.unwrap_or(1.0),
+        }
+    }
+}
+
+impl From<Args> for Config {
+    fn from(args: Args) -> Self {
+        Config::new(args, FileConfig::default())
+    }
+}
+
+// Reads the custom rules again when the config file has been modified.
+// The other settings take effect only on restart.
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified_at: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified_at = modified_time(&path);
+        ConfigWatcher { path, modified_at }
+    }
+
+    // None unless the file has been modified since the last call.
+    pub fn poll_custom_rules(&mut self) -> Option<Result<Vec<CustomRuleConfig>, ConfigError>> {
+        let modified_at = modified_time(&self.path);
+        if modified_at == self.modified_at {
+            return None;
+        }
+        self.modified_at = modified_at;
+        Some(read_file_config(&self.path).map(|file| file.custom_rules))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn read_file_config(path: &Path) -> Result<FileConfig, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|e| ConfigError::ReadError(path.to_owned(), e))?;
+    FileConfig::from_toml(&text).map_err(|e| ConfigError::ParseError(path.to_owned(), Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, clap::Error> {
+        let args = std::iter::once("vrc_doorkeeper").chain(args.iter().copied());
+        Args::try_parse_from(args).map(Config::from)
+    }
+
+    #[test]
+    fn config_uses_defaults_without_arguments() {
+        let expected = Config {
+            poll_interval: Duration::from_secs(1),
+            max_lines_per_poll: None,
+            overlay: Overlay::XsOverlay,
+            xsoverlay_host: "127.0.0.1".to_owned(),
+            xsoverlay_port: None,
+            xsoverlay_bind: "127.0.0.1:0".to_owned(),
+            desktop_fallback: false,
+            log_dir: None,
+            ambiguous_time: AmbiguousTimePolicy::Earliest,
+            osc_chatbox: false,
+            notify_instance_full: false,
+            notify_friend_presence: false,
+            notify_photo_taken: false,
+            notify_portal_dropped: false,
+            notify_master_changed: false,
+            notify_download_stalled: false,
+            notify_login: false,
+            no_login_on_first_read: false,
+            notify_world: false,
+            no_joins: false,
+            no_leaves: false,
+            no_quit: false,
+            no_moderation: false,
+            session_summary: false,
+            reconcile_roster: false,
+            show_world_name: false,
+            anonymize_names: None,
+            show_timestamp: false,
+            timestamp_format: None,
+            show_time_in_instance: false,
+            auto_height: false,
+            rejoin_window: None,
+            pass_through_window: None,
+            teardown_window: None,
+            disconnect_window: None,
+            load_quiet_period: None,
+            missing_log_grace: Duration::from_secs(60),
+            heartbeat_interval: None,
+            rate_summary_interval: None,
+            batch_window: None,
+            flap_window: None,
+            quiet_hours: None,
+            presence_timeout: None,
+            timeouts: EventTimeouts::default(),
+            icons: IconPaths::default(),
+            join_audio: None,
+            leave_audio: None,
+            custom_rules: Vec::new(),
+            no_welcome: false,
+            welcome_message: None,
+            index_limit: 10,
+            source_app: None,
+            control_port: None,
+            metrics_addr: None,
+            json_lines: None,
+            dry_run: false,
+            config_path: None,
+            once: false,
+            stdin: false,
+            trace_unmatched: false,
+            replay: None,
+            speed: 1.0,
+        };
+        assert_eq!(expected, parse(&[]).unwrap());
+    }
+
+    #[test]
+    fn config_reflects_arguments() {
+        let actual = parse(&[
+            "--poll-interval",
+            "250",
+            "--max-lines-per-poll",
+            "100",
+            "--overlay",
+            "ovr-toolkit",
+            "--xsoverlay-host",
+            "192.168.0.10",
+            "--xsoverlay-port",
+            "42070",
+            "--xsoverlay-bind",
+            "0.0.0.0:0",
+            "--desktop-fallback",
+            "--log-dir",
+            "D:\\logs",
+            "--ambiguous-time",
+            "latest",
+            "--osc-chatbox",
+            "--notify-instance-full",
+            "--notify-friend-presence",
+            "--notify-photo-taken",
+            "--notify-portal-dropped",
+            "--notify-master-changed",
+            "--notify-download-stalled",
+            "--session-summary",
+            "--reconcile-roster",
+            "--show-world-name",
+            "--anonymize-names",
+            "hashed",
+            "--show-timestamp",
+            "--timestamp-format",
+            "%H:%M:%S",
+            "--show-time-in-instance",
+            "--notify-login",
+            "--no-login-on-first-read",
+            "--notify-world",
+            "--no-joins",
+            "--no-leaves",
+            "--no-quit",
+            "--no-moderation",
+            "--auto-height",
+            "--rejoin-window",
+            "300",
+            "--pass-through-window",
+            "2",
+            "--teardown-window",
+            "1",
+            "--disconnect-window",
+            "30",
+            "--load-quiet-period",
+            "1500",
+            "--missing-log-grace",
+            "300",
+            "--heartbeat-interval",
+            "30",
+            "--rate-summary-interval",
+            "5",
+            "--no-welcome",
+            "--welcome-message",
+            "Watching the log.",
+            "--index-limit",
+            "4",
+            "--source-app",
+            "vrc_doorkeeper-alt",
+            "--control-port",
+            "42100",
+            "--metrics-addr",
+            "127.0.0.1:9100",
+            "--json-lines",
+            "-",
+            "--dry-run",
+            "--once",
+            "--stdin",
+            "--trace-unmatched",
+            "--replay",
+            "D:\\logs\\output_log.txt",
+            "--speed",
+            "2.5",
+        ])
+        .unwrap();
+        let expected = Config {
+            poll_interval: Duration::from_millis(250),
+            max_lines_per_poll: Some(100),
+            overlay: Overlay::OvrToolkit,
+            xsoverlay_host: "192.168.0.10".to_owned(),
+            xsoverlay_port: Some(42070),
+            xsoverlay_bind: "0.0.0.0:0".to_owned(),
+            desktop_fallback: true,
+            log_dir: Some(PathBuf::from("D:\\logs")),
+            ambiguous_time: AmbiguousTimePolicy::Latest,
+            osc_chatbox: true,
+            notify_instance_full: true,
+            notify_friend_presence: true,
+            notify_photo_taken: true,
+            notify_portal_dropped: true,
+            notify_master_changed: true,
+            notify_download_stalled: true,
+            notify_login: true,
+            no_login_on_first_read: true,
+            notify_world: true,
+            no_joins: true,
+            no_leaves: true,
+            no_quit: true,
+            no_moderation: true,
+            session_summary: true,
+            reconcile_roster: true,
+            show_world_name: true,
+            anonymize_names: Some(Anonymization::Hashed),
+            show_timestamp: true,
+            timestamp_format: Some("%H:%M:%S".to_owned()),
+            show_time_in_instance: true,
+            auto_height: true,
+            rejoin_window: Some(Duration::from_secs(300)),
+            pass_through_window: Some(Duration::from_secs(2)),
+            teardown_window: Some(Duration::from_secs(1)),
+            disconnect_window: Some(Duration::from_secs(30)),
+            load_quiet_period: Some(Duration::from_millis(1500)),
+            missing_log_grace: Duration::from_secs(300),
+            heartbeat_interval: Some(Duration::from_secs(30 * 60)),
+            rate_summary_interval: Some(Duration::from_secs(5 * 60)),
+            batch_window: None,
+            flap_window: None,
+            quiet_hours: None,
+            presence_timeout: None,
+            timeouts: EventTimeouts::default(),
+            icons: IconPaths::default(),
+            join_audio: None,
+            leave_audio: None,
+            custom_rules: Vec::new(),
+            no_welcome: true,
+            welcome_message: Some("Watching the log.".to_owned()),
+            index_limit: 4,
+            source_app: Some("vrc_doorkeeper-alt".to_owned()),
+            control_port: Some(42100),
+            metrics_addr: Some("127.0.0.1:9100".to_owned()),
+            json_lines: Some(PathBuf::from("-")),
+            dry_run: true,
+            config_path: None,
+            once: true,
+            stdin: true,
+            trace_unmatched: true,
+            replay: Some(PathBuf::from("D:\\logs\\output_log.txt")),
+            speed: 2.5,
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn config_rejects_invalid_values() {
+        assert!(parse(&["--poll-interval", "0"]).is_err());
+        assert!(parse(&["--poll-interval", "soon"]).is_err());
+        assert!(parse(&["--max-lines-per-poll", "0"]).is_err());
+        assert!(parse(&["--speed", "0"]).is_err());
+        assert!(parse(&["--speed", "-2"]).is_err());
+        assert!(parse(&["--speed", "fast"]).is_err());
+        assert!(parse(&["--xsoverlay-port", "0"]).is_err());
+        assert!(parse(&["--xsoverlay-port", "65536"]).is_err());
+        assert!(parse(&["--xsoverlay-port", "-1"]).is_err());
+        assert!(parse(&["--overlay", "steamvr"]).is_err());
+        assert!(parse(&["--control-port", "0"]).is_err());
+        assert!(parse(&["--index-limit", "0"]).is_err());
+        assert!(parse(&["--rate-summary-interval", "0"]).is_err());
+        assert!(parse(&["--ambiguous-time", "never"]).is_err());
+        assert!(parse(&["--anonymize-names", "blurred"]).is_err());
+        assert!(parse(&["--timestamp-format", "%H:%"]).is_err());
+    }
+
+    fn args(args: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("vrc_doorkeeper").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn config_reads_file() {
+        let file = FileConfig::from_toml(
+            r#"
+poll-interval = 500
+overlay = "ovr-toolkit"
+xsoverlay-port = 42070
+ambiguous-time = "skip"
+notify-friend-presence = true
+session-summary = true
+batch-window = 3
+flap-window = 10
+quiet-hours = { start = "23:00", end = "07:30" }
+timeouts = { presence = 1.5, warning = 5 }
+icons = { joined = "joined.png" }
+audio = { left = "C:\\sounds\\left.wav" }
+source-app = "vrc_doorkeeper-alt"
+
+[[custom-rules]]
+name = "dice"
+pattern = '^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)$'
+template = "{user} rolled {number}."
+"#,
+        )
+        .unwrap();
+        let config = Config::new(args(&[]), file);
+        assert_eq!(Duration::from_millis(500), config.poll_interval);
+        assert_eq!(Overlay::OvrToolkit, config.overlay);
+        assert_eq!(Some(42070), config.xsoverlay_port);
+        assert_eq!(AmbiguousTimePolicy::Skip, config.ambiguous_time);
+        assert!(config.notify_friend_presence);
+        assert!(config.session_summary);
+        assert_eq!(Some(Duration::from_secs(3)), config.batch_window);
+        assert_eq!(Some(Duration::from_secs(10)), config.flap_window);
+        assert_eq!(
+            Some(QuietHours {
+                start: NaiveTime::from_hms(23, 0, 0),
+                end: NaiveTime::from_hms(7, 30, 0),
+            }),
+            config.quiet_hours
+        );
+        assert_eq!(Some(1.5), config.presence_timeout);
+        assert_eq!(
+            EventTimeouts {
+                info: 2.0,
+                warning: 5.0,
+            },
+            config.timeouts
+        );
+        assert_eq!(
+            IconPaths {
+                joined: Some(PathBuf::from("joined.png")),
+                ..Default::default()
+            },
+            config.icons
+        );
+        assert_eq!(None, config.join_audio);
+        assert_eq!(Some("C:\\sounds\\left.wav".to_owned()), config.leave_audio);
+        assert_eq!(Some("vrc_doorkeeper-alt".to_owned()), config.source_app);
+        assert_eq!(
+            vec![CustomRuleConfig {
+                name: "dice".to_owned(),
+                pattern: r"^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)$".to_owned(),
+                template: "{user} rolled {number}.".to_owned(),
+            }],
+            config.custom_rules
+        );
+        // the missing keys keep the defaults.
+        assert_eq!("127.0.0.1", config.xsoverlay_host);
+        assert!(!config.notify_instance_full);
+        assert_eq!(None, config.rejoin_window);
+    }
+
+    #[test]
+    fn config_prefers_arguments_to_file() {
+        let file =
+            FileConfig::from_toml("poll-interval = 500\nsource-app = \"from-file\"").unwrap();
+        let config = Config::new(
+            args(&["--poll-interval", "250", "--source-app", "from-args"]),
+            file,
+        );
+        assert_eq!(Duration::from_millis(250), config.poll_interval);
+        assert_eq!(Some("from-args".to_owned()), config.source_app);
+    }
+
+    #[test]
+    fn config_rejects_invalid_file() {
+        assert!(FileConfig::from_toml("poll-intervl = 500").is_err());
+        assert!(FileConfig::from_toml("poll-interval = \"soon\"").is_err());
+        assert!(FileConfig::from_toml("overlay = \"steamvr\"").is_err());
+        assert!(FileConfig::from_toml("timestamp-format = \"%Q\"").is_err());
+        assert!(
+            FileConfig::from_toml("quiet-hours = { start = \"25:00\", end = \"07:00\" }").is_err()
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vrc_doorkeeper.toml");
+        fs::write(&path, "poll-interval = ").unwrap();
+        let error = Config::load(args(&["--config", path.to_str().unwrap()])).unwrap_err();
+        assert!(matches!(error, ConfigError::ParseError(..)));
+        assert!(error.to_string().starts_with("invalid config in "));
+
+        let error = Config::load(args(&[
+            "--config",
+            dir.path().join("missing.toml").to_str().unwrap(),
+        ]))
+        .unwrap_err();
+        assert!(matches!(error, ConfigError::ReadError(..)));
+    }
+
+    #[test]
+    fn config_watcher_reloads_custom_rules_of_modified_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vrc_doorkeeper.toml");
+        let write = |pattern: &str, modified_at: SystemTime| {
+            let text = format!(
+                "[[custom-rules]]\nname = \"dice\"\npattern = '{}'\ntemplate = \"{{user}}\"\n",
+                pattern
+            );
+            fs::write(&path, text).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified_at)
+                .unwrap();
+        };
+        let now = SystemTime::now();
+        write(r"^\[DiceMod\] ", now);
+        let mut watcher = ConfigWatcher::new(path.clone());
+        assert!(watcher.poll_custom_rules().is_none());
+
+        write(r"rolled \d+$", now + Duration::from_secs(1));
+        let rules = watcher.poll_custom_rules().unwrap().unwrap();
+        assert_eq!(r"rolled \d+$", rules[0].pattern);
+        assert!(watcher.poll_custom_rules().is_none());
+
+        write("(", now + Duration::from_secs(2));
+        let rules = watcher.poll_custom_rules().unwrap().unwrap();
+        assert!(compile_custom_rules(&rules).is_err());
+    }
+}
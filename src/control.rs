@@ -0,0 +1,87 @@
+use std::io;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    Mute,
+    Unmute,
+    Toggle,
+}
+
+// Commands are plain text, e.g. `echo mute | ncat -u 127.0.0.1 <port>`.
+pub fn parse_command(text: &str) -> Option<ControlCommand> {
+    match text.trim().to_ascii_lowercase().as_str() {
+        "mute" => Some(ControlCommand::Mute),
+        "unmute" => Some(ControlCommand::Unmute),
+        "toggle" => Some(ControlCommand::Toggle),
+        _ => None,
+    }
+}
+
+// Returns whether notifications are muted after the command.
+pub fn apply_command(muted: &AtomicBool, command: ControlCommand) -> bool {
+    match command {
+        ControlCommand::Mute => muted.store(true, Ordering::SeqCst),
+        ControlCommand::Unmute => muted.store(false, Ordering::SeqCst),
+        ControlCommand::Toggle => {
+            muted.fetch_xor(true, Ordering::SeqCst);
+        }
+    }
+    muted.load(Ordering::SeqCst)
+}
+
+// Listens for the commands on localhost in the background.
+pub fn spawn_control_listener(port: u16, muted: Arc<AtomicBool>) -> io::Result<()> {
+    let socket = UdpSocket::bind(format!("127.0.0.1:{}", port))?;
+    thread::spawn(move || {
+        let mut buf = [0u8; 64];
+        loop {
+            let len = match socket.recv_from(&mut buf) {
+                Ok((len, _)) => len,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            let text = String::from_utf8_lossy(&buf[..len]);
+            match parse_command(&text) {
+                Some(command) => {
+                    let is_muted = apply_command(&muted, command);
+                    println!(
+                        "Notifications are {}.",
+                        if is_muted { "muted" } else { "unmuted" }
+                    );
+                }
+                None => eprintln!("Unknown control command: {}", text.trim()),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command_accepts_known_commands() {
+        assert_eq!(Some(ControlCommand::Mute), parse_command("mute\n"));
+        assert_eq!(Some(ControlCommand::Unmute), parse_command(" UNMUTE "));
+        assert_eq!(Some(ControlCommand::Toggle), parse_command("toggle"));
+        assert_eq!(None, parse_command("shutdown"));
+        assert_eq!(None, parse_command(""));
+    }
+
+    #[test]
+    fn apply_command_flips_muted_flag() {
+        let muted = AtomicBool::new(false);
+        assert!(apply_command(&muted, ControlCommand::Mute));
+        assert!(apply_command(&muted, ControlCommand::Mute));
+        assert!(!apply_command(&muted, ControlCommand::Toggle));
+        assert!(apply_command(&muted, ControlCommand::Toggle));
+        assert!(!apply_command(&muted, ControlCommand::Unmute));
+    }
+}
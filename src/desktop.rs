@@ -0,0 +1,138 @@
+use std::io;
+
+use notify_rust::{Notification, Timeout};
+
+use crate::xsoverlay::{LivenessProbe, MessageObject, NotificationSink, SendMessageError};
+
+// Shows notifications on the desktop with the native notification API.
+pub struct DesktopNotificationClient {
+    app_name: String,
+}
+
+impl DesktopNotificationClient {
+    pub fn new() -> DesktopNotificationClient {
+        DesktopNotificationClient {
+            app_name: "VRC Doorkeeper".to_owned(),
+        }
+    }
+}
+
+impl Default for DesktopNotificationClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationSink for DesktopNotificationClient {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        Notification::new()
+            .appname(&self.app_name)
+            .summary(message.title())
+            .body(message.content())
+            .timeout(Timeout::Milliseconds((message.timeout() * 1000f32) as u32))
+            .show()
+            .map(|_| ())
+            .map_err(|e| SendMessageError::SendError(io::Error::other(e)))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FallbackMode {
+    Disabled,
+    // Use the fallback only when the probe reports that XSOverlay is down.
+    WhenUnreachable,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SinkChoice {
+    Primary,
+    Fallback,
+}
+
+// The probe is not run at all when the fallback is disabled.
+pub fn select_sink<F>(mode: FallbackMode, is_alive: F) -> SinkChoice
+where
+    F: FnOnce() -> bool,
+{
+    match mode {
+        FallbackMode::Disabled => SinkChoice::Primary,
+        FallbackMode::WhenUnreachable => {
+            if is_alive() {
+                SinkChoice::Primary
+            } else {
+                SinkChoice::Fallback
+            }
+        }
+    }
+}
+
+pub struct FallbackNotificationClient<S, F, P>
+where
+    S: NotificationSink,
+    F: NotificationSink,
+    P: LivenessProbe,
+{
+    primary: S,
+    fallback: F,
+    probe: P,
+    mode: FallbackMode,
+}
+
+impl<S, F, P> FallbackNotificationClient<S, F, P>
+where
+    S: NotificationSink,
+    F: NotificationSink,
+    P: LivenessProbe,
+{
+    pub fn new(primary: S, fallback: F, probe: P, mode: FallbackMode) -> Self {
+        FallbackNotificationClient {
+            primary,
+            fallback,
+            probe,
+            mode,
+        }
+    }
+}
+
+impl<S, F, P> NotificationSink for FallbackNotificationClient<S, F, P>
+where
+    S: NotificationSink,
+    F: NotificationSink,
+    P: LivenessProbe,
+{
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        match select_sink(self.mode, || self.probe.is_alive()) {
+            SinkChoice::Primary => self.primary.send_message(message),
+            SinkChoice::Fallback => self.fallback.send_message(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn select_sink_does_not_probe_when_disabled() {
+        let probed = Cell::new(false);
+        let choice = select_sink(FallbackMode::Disabled, || {
+            probed.set(true);
+            false
+        });
+        assert_eq!(SinkChoice::Primary, choice);
+        assert!(!probed.get());
+    }
+
+    #[test]
+    fn select_sink_uses_primary_when_overlay_is_alive() {
+        let choice = select_sink(FallbackMode::WhenUnreachable, || true);
+        assert_eq!(SinkChoice::Primary, choice);
+    }
+
+    #[test]
+    fn select_sink_uses_fallback_when_overlay_is_down() {
+        let choice = select_sink(FallbackMode::WhenUnreachable, || false);
+        assert_eq!(SinkChoice::Fallback, choice);
+    }
+}
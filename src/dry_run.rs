@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+use crate::xsoverlay::{MessageObject, NotificationSink, SendMessageError};
+
+// Writes the notifications instead of sending them, for checking what would be notified.
+pub struct DryRunNotificationClient<W: Write> {
+    writer: RefCell<W>,
+}
+
+impl DryRunNotificationClient<io::Stdout> {
+    pub fn new() -> Self {
+        Self::new_with_writer(io::stdout())
+    }
+}
+
+impl Default for DryRunNotificationClient<io::Stdout> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write> DryRunNotificationClient<W> {
+    pub fn new_with_writer(writer: W) -> Self {
+        DryRunNotificationClient {
+            writer: RefCell::new(writer),
+        }
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer.into_inner()
+    }
+}
+
+impl<W: Write> NotificationSink for DryRunNotificationClient<W> {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        let mut writer = self.writer.borrow_mut();
+        if message.content().is_empty() {
+            writeln!(writer, "[notification] {}", message.title())?;
+        } else {
+            writeln!(
+                writer,
+                "[notification] {}: {}",
+                message.title(),
+                message.content()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), SendMessageError> {
+        self.writer.borrow_mut().flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xsoverlay::MessageObjectBuilder;
+
+    #[test]
+    fn dry_run_client_records_messages_instead_of_sending() {
+        let client = DryRunNotificationClient::new_with_writer(Vec::new());
+        client
+            .send_message(&MessageObjectBuilder::new("Alice joined.".to_owned()).build())
+            .unwrap();
+        client
+            .send_message(
+                &MessageObjectBuilder::new("The instance is full.".to_owned())
+                    .set_content("Friends trying to join can't get in.".to_owned())
+                    .build(),
+            )
+            .unwrap();
+        let output = String::from_utf8(client.into_writer()).unwrap();
+        assert_eq!(
+            "[notification] Alice joined.\n[notification] The instance is full.: Friends trying to join can't get in.\n",
+            output
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::Write;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::reader::LogLineProcessor;
+use crate::vrc::{Event, ParsedEvent};
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+// e.g. `{"time":"2021-12-01T23:23:13+09:00","event":"OnPlayerJoined","user":"paralleltree"}`
+pub fn to_json_line(time: &DateTime<Local>, event: &Event) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&EventLine {
+        time: time.to_rfc3339(),
+        event,
+    })
+}
+
+// Writes every event appended to the log as a line of JSON.
+pub struct JsonLineSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLineSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonLineSink { writer }
+    }
+
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> LogLineProcessor for JsonLineSink<W> {
+    fn process_event(&mut self, event: ParsedEvent, is_first: bool) {
+        if is_first {
+            return;
+        }
+        let result = to_json_line(&event.time, &event.event)
+            .map_err(std::io::Error::from)
+            .and_then(|json| writeln!(self.writer, "{}", json));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use crate::vrc::log::LogLine;
+    use crate::vrc::{InstanceAccessType, ModerationKind, Region};
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn assert_json(event: Event, expected: serde_json::Value) {
+        let time = Local.ymd(2021, 12, 1).and_hms(23, 23, 13);
+        let actual: serde_json::Value =
+            serde_json::from_str(&to_json_line(&time, &event).unwrap()).unwrap();
+        let mut expected = expected;
+        expected["time"] = json!(time.to_rfc3339());
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn json_line_serializes_room_events() {
+        assert_json(Event::OnJoinedRoom, json!({"event": "OnJoinedRoom"}));
+        assert_json(Event::OnLeftRoom, json!({"event": "OnLeftRoom"}));
+        assert_json(Event::ApplicationQuit, json!({"event": "ApplicationQuit"}));
+        assert_json(Event::InstanceFull, json!({"event": "InstanceFull"}));
+    }
+
+    #[test]
+    fn json_line_serializes_player_events() {
+        for (event, name) in [
+            (
+                Event::OnPlayerJoined {
+                    user_name: "paralleltree".to_owned(),
+                },
+                "OnPlayerJoined",
+            ),
+            (
+                Event::OnPlayerLeft {
+                    user_name: "paralleltree".to_owned(),
+                },
+                "OnPlayerLeft",
+            ),
+            (
+                Event::UserAuthenticated {
+                    user_name: "paralleltree".to_owned(),
+                },
+                "UserAuthenticated",
+            ),
+        ] {
+            assert_json(event, json!({"event": name, "user": "paralleltree"}));
+        }
+    }
+
+    #[test]
+    fn json_line_serializes_instance_info_event() {
+        assert_json(
+            Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(jp)"
+                    .to_owned(),
+                access_type: InstanceAccessType::InvitePlus,
+                region: Some(Region::Japan),
+            },
+            json!({
+                "event": "InstanceInfo",
+                "instance_id": "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(jp)",
+                "access_type": "InvitePlus",
+                "region": "jp",
+            }),
+        );
+        assert_json(
+            Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345".to_owned(),
+                access_type: InstanceAccessType::Custom("somethingNew".to_owned()),
+                region: None,
+            },
+            json!({
+                "event": "InstanceInfo",
+                "instance_id": "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345",
+                "access_type": {"Custom": "somethingNew"},
+                "region": null,
+            }),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_video_playing_event() {
+        assert_json(
+            Event::VideoPlaying {
+                url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned(),
+                requested_by: Some("paralleltree".to_owned()),
+            },
+            json!({
+                "event": "VideoPlaying",
+                "url": "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+                "requested_by": "paralleltree",
+            }),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_friend_presence_event() {
+        assert_json(
+            Event::FriendPresence {
+                user_name: "paralleltree".to_owned(),
+                online: true,
+            },
+            json!({"event": "FriendPresence", "user": "paralleltree", "online": true}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_photo_taken_event() {
+        assert_json(
+            Event::PhotoTaken {
+                path: "C:\\Pictures\\VRChat\\VRChat_2021-12-01.png".to_owned(),
+            },
+            json!({"event": "PhotoTaken", "path": "C:\\Pictures\\VRChat\\VRChat_2021-12-01.png"}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_moderation_event() {
+        assert_json(
+            Event::Moderation {
+                kind: ModerationKind::Kick,
+                target: Some("paralleltree".to_owned()),
+            },
+            json!({"event": "Moderation", "kind": "Kick", "target": "paralleltree"}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_entering_room_event() {
+        assert_json(
+            Event::EnteringRoom {
+                world_name: "The Great Pug".to_owned(),
+            },
+            json!({"event": "EnteringRoom", "world_name": "The Great Pug"}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_custom_event() {
+        assert_json(
+            Event::Custom {
+                name: "dice".to_owned(),
+                captures: HashMap::from([
+                    ("user".to_owned(), "paralleltree".to_owned()),
+                    ("roll".to_owned(), "6".to_owned()),
+                ]),
+            },
+            json!({
+                "event": "Custom",
+                "name": "dice",
+                "captures": {"user": "paralleltree", "roll": "6"},
+            }),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_player_count_event() {
+        assert_json(
+            Event::PlayerCount { count: 12 },
+            json!({"event": "PlayerCount", "count": 12}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_portal_dropped_event() {
+        assert_json(
+            Event::PortalDropped {
+                user_name: Some("paralleltree".to_owned()),
+                world: Some("The Great Pug".to_owned()),
+            },
+            json!({"event": "PortalDropped", "user": "paralleltree", "world": "The Great Pug"}),
+        );
+        assert_json(
+            Event::PortalDropped {
+                user_name: None,
+                world: None,
+            },
+            json!({"event": "PortalDropped", "user": null, "world": null}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_master_changed_event() {
+        assert_json(
+            Event::MasterChanged {
+                user_name: "paralleltree".to_owned(),
+            },
+            json!({"event": "MasterChanged", "user": "paralleltree"}),
+        );
+    }
+
+    #[test]
+    fn json_line_serializes_asset_download_stalled_event() {
+        assert_json(
+            Event::AssetDownloadStalled {
+                what: "World (The Great Pug)".to_owned(),
+            },
+            json!({"event": "AssetDownloadStalled", "what": "World (The Great Pug)"}),
+        );
+    }
+
+    #[test]
+    fn json_line_sink_writes_only_appended_events() {
+        let mut sink = JsonLineSink::new(Vec::new());
+        let line = |body: &str| {
+            LogLine::from_line(&format!("2021.12.01 23:23:13 Log        -  {}", body)).unwrap()
+        };
+        sink.process_line(line("[Behaviour] OnPlayerJoined Alice"), true);
+        sink.process_line(line("[Behaviour] Some unrelated message"), false);
+        sink.process_line(line("[Behaviour] OnPlayerJoined Bob"), false);
+        let output = String::from_utf8(sink.into_writer()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(1, lines.len());
+        assert!(lines[0].contains("\"user\":\"Bob\""));
+    }
+}
@@ -0,0 +1,18 @@
+pub mod assets;
+pub mod control;
+pub mod desktop;
+pub mod dry_run;
+pub mod json_line;
+pub mod metrics;
+pub mod notifier;
+pub mod ovrtoolkit;
+#[cfg(feature = "async")]
+pub mod pipeline;
+pub mod reader;
+pub mod replay;
+pub mod stream;
+#[cfg(test)]
+mod testing;
+pub mod vrc;
+pub mod watchdog;
+pub mod xsoverlay;
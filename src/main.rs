@@ -1,123 +1,303 @@
-mod assets;
-mod reader;
-mod vrc;
-mod xsoverlay;
+mod cli;
 
-use chrono::{DateTime, Duration, Utc};
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::rc::Rc;
 
-use crate::reader::{LogLineProcessor, VrChatLogProcessor};
-use crate::vrc::log::LogLine;
-use crate::xsoverlay::{MessageObjectBuilder, NotificationClient};
+use clap::Parser;
 
-struct VrcToXsOverlayNotifier<C>
-where
-    C: CurrentTimeProvider,
-{
-    client: xsoverlay::NotificationClient,
-    // The last time of OnJoinedRoom or OnLeftRoom detected.
-    // At the end of DST, the time provided from log file may be ambiguous.
-    // so this field must be assigned with current system time.
-    // This field is used to determine whether the join or left event is not caused by moving world.
-    notifiable_since: Option<DateTime<Utc>>,
-    current_time_provider: C,
-}
+use vrc_doorkeeper::control::spawn_control_listener;
+use vrc_doorkeeper::desktop::{
+    DesktopNotificationClient, FallbackMode, FallbackNotificationClient,
+};
+use vrc_doorkeeper::dry_run::DryRunNotificationClient;
+use vrc_doorkeeper::json_line::JsonLineSink;
+use vrc_doorkeeper::metrics::spawn_metrics_server;
+use vrc_doorkeeper::notifier::{
+    send_welcome, DefaultCurrentTimeProvider, LoadGate, NotifierSettings, VrcToXsOverlayNotifier,
+};
+use vrc_doorkeeper::ovrtoolkit::OvrToolkitClient;
+use vrc_doorkeeper::reader::{
+    open_log, BufReadSource, LineSourceProcessor, LogLineProcessor, VrChatLogProcessor,
+};
+use vrc_doorkeeper::replay::ReplayPacer;
+use vrc_doorkeeper::vrc;
+use vrc_doorkeeper::vrc::log::SharedCustomRules;
+use vrc_doorkeeper::vrc::osc::{OscChatboxClient, OscChatboxSink};
+use vrc_doorkeeper::watchdog::MissingLogWatchdog;
+use vrc_doorkeeper::xsoverlay::{
+    self, CachedLivenessProbe, NotificationAudio, NotificationClient, NotificationSink,
+    TcpLivenessProbe,
+};
 
-impl<C: CurrentTimeProvider> VrcToXsOverlayNotifier<C> {
-    fn new(client: xsoverlay::NotificationClient, current_time_provider: C) -> Self {
-        VrcToXsOverlayNotifier {
-            client,
-            notifiable_since: None,
-            current_time_provider,
-        }
-    }
+use crate::cli::{compile_custom_rules, Args, Config, ConfigWatcher, Overlay};
 
-    fn to_notification_object(&self, line: vrc::log::LogLine) -> Option<xsoverlay::MessageObject> {
-        if let Some(notifiable_since) = self.notifiable_since {
-            if self.current_time_provider.current_time() < notifiable_since {
-                return None;
-            }
+fn main() {
+    let config = Config::load(Args::parse()).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let client: Rc<dyn NotificationSink> = if config.dry_run {
+        Rc::new(DryRunNotificationClient::new())
+    } else {
+        match config.overlay {
+            Overlay::XsOverlay => Rc::new(FallbackNotificationClient::new(
+                NotificationClient::new_with_bind(
+                    &config.xsoverlay_bind,
+                    &config.xsoverlay_host,
+                    config
+                        .xsoverlay_port
+                        .unwrap_or_else(xsoverlay::discover_env_port),
+                )
+                .expect("Failed to initialize NotificationClient."),
+                DesktopNotificationClient::new(),
+                CachedLivenessProbe::new(
+                    TcpLivenessProbe::new_with_host(&config.xsoverlay_host),
+                    DefaultCurrentTimeProvider {},
+                    xsoverlay::DEFAULT_LIVENESS_TTL,
+                ),
+                if config.desktop_fallback {
+                    FallbackMode::WhenUnreachable
+                } else {
+                    FallbackMode::Disabled
+                },
+            )),
+            Overlay::OvrToolkit => Rc::new(OvrToolkitClient::new()),
         }
+    };
+    let mut message_defaults = NotifierSettings::default().message_defaults;
+    if let Some(source_app) = config.source_app {
+        message_defaults.source_app = source_app;
+    }
+    if let Some(timeout) = config.presence_timeout {
+        message_defaults.timeout = timeout;
+    }
+    let custom_rules = compile_custom_rules(&config.custom_rules).unwrap_or_else(|e| {
+        eprintln!("Invalid pattern of a custom rule: {}", e);
+        std::process::exit(1);
+    });
+    let custom_rules = SharedCustomRules::new(custom_rules);
+    let settings = NotifierSettings {
+        notify_instance_full: config.notify_instance_full,
+        notify_friend_presence: config.notify_friend_presence,
+        notify_photo_taken: config.notify_photo_taken,
+        notify_portal_dropped: config.notify_portal_dropped,
+        notify_master_changed: config.notify_master_changed,
+        notify_download_stalled: config.notify_download_stalled,
+        notify_session_summary: config.session_summary,
+        reconcile_roster: config.reconcile_roster,
+        show_world_name: config.show_world_name,
+        anonymize_names: config.anonymize_names,
+        show_timestamp: config.show_timestamp,
+        timestamp_format: config
+            .timestamp_format
+            .unwrap_or(NotifierSettings::default().timestamp_format),
+        show_time_in_instance: config.show_time_in_instance,
+        notify_authenticated: config.notify_login,
+        notify_authenticated_on_first_read: !config.no_login_on_first_read,
+        notify_world: config.notify_world,
+        notify_join: !config.no_joins,
+        notify_leave: !config.no_leaves,
+        notify_application_quit: !config.no_quit,
+        notify_moderation: !config.no_moderation,
+        auto_height: config.auto_height,
+        batch_window: config
+            .batch_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        flap_window: config
+            .flap_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        quiet_hours: config.quiet_hours,
+        rejoin_window: config
+            .rejoin_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        pass_through_window: config
+            .pass_through_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        teardown_window: config
+            .teardown_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        disconnect_window: config
+            .disconnect_window
+            .map(|window| chrono::Duration::from_std(window).unwrap()),
+        heartbeat_interval: config
+            .heartbeat_interval
+            .map(|interval| chrono::Duration::from_std(interval).unwrap()),
+        rate_summary_interval: config
+            .rate_summary_interval
+            .map(|interval| chrono::Duration::from_std(interval).unwrap()),
+        message_defaults: message_defaults.clone(),
+        timeouts: config.timeouts,
+        icons: config.icons,
+        join_audio: config
+            .join_audio
+            .map(NotificationAudio::Custom)
+            .unwrap_or_default(),
+        leave_audio: config
+            .leave_audio
+            .map(NotificationAudio::Custom)
+            .unwrap_or_default(),
+        load_gate: match config.load_quiet_period {
+            Some(period) => LoadGate::Quiescence(chrono::Duration::from_std(period).unwrap()),
+            None => LoadGate::default(),
+        },
+        index_limit: Some(config.index_limit),
+        // a safety net against reading the same lines twice.
+        dedup_window: Some(chrono::Duration::seconds(5)),
+        welcome_message: if config.no_welcome {
+            None
+        } else {
+            config
+                .welcome_message
+                .or(NotifierSettings::default().welcome_message)
+        },
+        ..Default::default()
+    };
+    // a single pass is usually run by a script, so it does not greet.
+    if !config.once && !config.stdin {
+        send_welcome(&*client, &settings);
+    }
 
-        let (title, icon) = match line.event? {
-            vrc::Event::OnPlayerJoined { user_name } => (
-                format!("{} joined.", user_name),
-                &*assets::ON_PLAYER_JOINED_ROOM_ICON,
-            ),
-            vrc::Event::OnPlayerLeft { user_name } => (
-                format!("{} left.", user_name),
-                &*assets::ON_PLAYER_LEFT_ROOM_ICON,
-            ),
-            _ => return None,
+    let mut notifier =
+        VrcToXsOverlayNotifier::new(Rc::clone(&client), DefaultCurrentTimeProvider {}, settings);
+    // others in the instance can read the chatbox, so it only shows who joined and left.
+    if config.osc_chatbox && !config.dry_run {
+        let chatbox = OscChatboxClient::new().expect("Failed to initialize OscChatboxClient.");
+        notifier.set_presence_sink(OscChatboxSink::new(chatbox));
+    }
+    notifier.set_custom_rules(custom_rules.clone());
+    if let Some(port) = config.control_port {
+        spawn_control_listener(port, notifier.muted_flag())
+            .expect("Failed to start the control listener.");
+    }
+    if let Some(addr) = &config.metrics_addr {
+        spawn_metrics_server(addr, notifier.metrics())
+            .expect("Failed to start the metrics server.");
+    }
+    let mut processors: Vec<Box<dyn LogLineProcessor>> = vec![Box::new(notifier)];
+    if let Some(path) = &config.json_lines {
+        let writer: Box<dyn Write> = if path.as_os_str() == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .expect("Failed to open the JSON lines output.");
+            Box::new(BufWriter::new(file))
         };
+        processors.push(Box::new(JsonLineSink::new(writer)));
+    }
 
-        let message = MessageObjectBuilder::new(title)
-            .set_icon(xsoverlay::NotificationType::Custom(String::from(icon)), true)
-            .set_timeout(1f32)
-            .build();
-        Some(message)
+    if let Some(path) = &config.replay {
+        let log = open_log(path).unwrap_or_else(|e| {
+            eprintln!("Failed to open {}: {}", path.display(), e);
+            std::process::exit(1);
+        });
+        let pacer = ReplayPacer::new(processors, config.speed, std::thread::sleep);
+        let mut processor = LineSourceProcessor::new(BufReadSource::new(log), pacer);
+        processor.set_time_policy(config.ambiguous_time);
+        processor.set_custom_rules(custom_rules);
+        processor.set_trace_unmatched(config.trace_unmatched);
+        let result = processor.process_lines();
+        // what is held back at the end of the log, e.g. a batch, is sent in the meantime.
+        processor
+            .processor_mut()
+            .linger(std::time::Duration::from_secs(10));
+        report_header_mismatches(processor.header_mismatches());
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-trait CurrentTimeProvider {
-    fn current_time(&self) -> DateTime<Utc>;
-}
+    if config.stdin {
+        let source = BufReadSource::new(io::stdin().lock());
+        let mut processor = LineSourceProcessor::new(source, processors);
+        processor.set_time_policy(config.ambiguous_time);
+        processor.set_custom_rules(custom_rules);
+        processor.set_trace_unmatched(config.trace_unmatched);
+        let result = processor.process_lines();
+        report_header_mismatches(processor.header_mismatches());
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-struct DefaultCurrentTimeProvider {}
+    let log_dir = match config.log_dir {
+        Some(log_dir) => log_dir,
+        None => vrc::log::get_log_dir_path().unwrap_or_else(|e| {
+            eprintln!(
+                "Failed to locate the VRChat log directory ({}). Specify it with --log-dir.",
+                e
+            );
+            std::process::exit(1);
+        }),
+    };
+    let mut processor = VrChatLogProcessor::new(log_dir, processors);
+    processor.set_time_policy(config.ambiguous_time);
+    processor.set_custom_rules(custom_rules.clone());
+    processor.set_trace_unmatched(config.trace_unmatched);
 
-impl CurrentTimeProvider for DefaultCurrentTimeProvider {
-    fn current_time(&self) -> DateTime<Utc> {
-        Utc::now()
+    if config.once {
+        let result = processor.process_log();
+        report_header_mismatches(processor.header_mismatches());
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        // there was no log to read.
+        if processor.is_waiting_for_log_dir() {
+            std::process::exit(1);
+        }
+        return;
     }
-}
 
-impl<C: CurrentTimeProvider> LogLineProcessor for VrcToXsOverlayNotifier<C> {
-    fn process_line(&mut self, line: LogLine, is_first: bool) {
-        if is_first {
-            // do not send any notification.
-            return;
-        }
+    // a single pass reads to the end of the log, so only polling is limited.
+    processor.set_line_limit(config.max_lines_per_poll);
+    let mut watchdog = MissingLogWatchdog::new(
+        client,
+        DefaultCurrentTimeProvider {},
+        chrono::Duration::from_std(config.missing_log_grace).unwrap(),
+    );
+    watchdog.set_message_defaults(message_defaults);
+    let mut config_watcher = config.config_path.map(ConfigWatcher::new);
+    let mut reported_header_mismatches = processor.header_mismatches();
 
-        if let Some(event) = &line.event {
-            match event {
-                vrc::Event::OnJoinedRoom | vrc::Event::OnLeftRoom => {
-                    // store the time that sending notification starts.
-                    self.notifiable_since =
-                        Some(self.current_time_provider.current_time() + Duration::seconds(5));
-                }
-                _ => (),
-            }
+    loop {
+        let result = processor.process_log();
+        // reported only when it grows, not to repeat it every poll.
+        if processor.header_mismatches() != reported_header_mismatches {
+            reported_header_mismatches = processor.header_mismatches();
+            report_header_mismatches(reported_header_mismatches);
         }
-
-        if let Some(message) = self.to_notification_object(line) {
-            match self.client.send_message(&message) {
-                Ok(()) => (),
-                Err(e) => match e {
-                    xsoverlay::SendMessageError::JsonError(e) => eprintln!("{}", e),
-                    xsoverlay::SendMessageError::SendError(e) => eprintln!("{}", e),
-                },
+        if let Err(e) = &result {
+            println!("{}", e);
+        }
+        if processor.is_waiting_for_log_dir() {
+            watchdog.record_missing();
+        } else {
+            watchdog.observe(&result);
+        }
+        if let Some(result) = config_watcher.as_mut().and_then(|w| w.poll_custom_rules()) {
+            match result.map(|rules| compile_custom_rules(&rules)) {
+                Ok(Ok(rules)) => {
+                    custom_rules.replace(rules);
+                    eprintln!("Reloaded the custom rules.");
+                }
+                Ok(Err(e)) => eprintln!("Invalid pattern of a custom rule: {}", e),
+                Err(e) => eprintln!("{}", e),
             }
         }
+        std::thread::sleep(config.poll_interval);
     }
 }
 
-fn main() {
-    let client = NotificationClient::new().expect("Failed to initialize NotificationClient.");
-    let welcome = MessageObjectBuilder::new("VRC Doorkeeper".to_owned())
-        .set_content("Join and Leave notifications are enabled.".to_owned())
-        .set_timeout(2f32)
-        .build();
-    client
-        .send_message(&welcome)
-        .expect("Failed to send message.");
-
-    let mut notifier = VrcToXsOverlayNotifier::new(client, DefaultCurrentTimeProvider {});
-    let mut processor = VrChatLogProcessor::new(vrc::log::get_log_dir_path(), &mut notifier);
-
-    loop {
-        match processor.process_log() {
-            Ok(()) => (),
-            Err(e) => println!("{}", e),
-        };
-        std::thread::sleep(std::time::Duration::from_secs(1));
+fn report_header_mismatches(header_mismatches: Option<u64>) {
+    if let Some(count) = header_mismatches {
+        eprintln!("{} lines without the log header so far.", count);
     }
 }
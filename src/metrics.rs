@@ -0,0 +1,235 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::vrc::Event;
+
+// The names of the events in the order they are exposed, as Event::name returns them.
+const EVENT_NAMES: [&str; 18] = [
+    "EnteringRoom",
+    "OnJoinedRoom",
+    "OnPlayerJoined",
+    "OnLeftRoom",
+    "OnPlayerLeft",
+    "UserAuthenticated",
+    "InstanceInfo",
+    "ApplicationQuit",
+    "VideoPlaying",
+    "InstanceFull",
+    "PlayerCount",
+    "FriendPresence",
+    "PhotoTaken",
+    "MasterChanged",
+    "PortalDropped",
+    "AssetDownloadStalled",
+    "Moderation",
+    "Custom",
+];
+
+// Counters shared between the notifier and the metrics server.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    events: [AtomicU64; EVENT_NAMES.len()],
+    notifications_sent: AtomicU64,
+    send_failures: AtomicU64,
+    roster_size: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_event(&self, event: &Event) {
+        if let Some(index) = EVENT_NAMES.iter().position(|name| *name == event.name()) {
+            self.events[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_send(&self, succeeded: bool) {
+        let counter = if succeeded {
+            &self.notifications_sent
+        } else {
+            &self.send_failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_roster_size(&self, size: usize) {
+        self.roster_size.store(size as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            events: EVENT_NAMES
+                .iter()
+                .zip(&self.events)
+                .map(|(name, count)| (*name, count.load(Ordering::Relaxed)))
+                .collect(),
+            notifications_sent: self.notifications_sent.load(Ordering::Relaxed),
+            send_failures: self.send_failures.load(Ordering::Relaxed),
+            roster_size: self.roster_size.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    // Every kind of event, including the ones never parsed.
+    pub events: Vec<(&'static str, u64)>,
+    pub notifications_sent: u64,
+    pub send_failures: u64,
+    pub roster_size: u64,
+}
+
+impl MetricsSnapshot {
+    // Renders the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        text.push_str("# HELP vrc_doorkeeper_events_total Events parsed from the log.\n");
+        text.push_str("# TYPE vrc_doorkeeper_events_total counter\n");
+        for (name, count) in &self.events {
+            writeln!(
+                text,
+                "vrc_doorkeeper_events_total{{event=\"{}\"}} {}",
+                name, count
+            )
+            .unwrap();
+        }
+        let metrics = [
+            (
+                "notifications_sent_total",
+                "counter",
+                "Notifications sent.",
+                self.notifications_sent,
+            ),
+            (
+                "send_failures_total",
+                "counter",
+                "Notifications failed to send.",
+                self.send_failures,
+            ),
+            (
+                "roster_size",
+                "gauge",
+                "Players currently in the instance.",
+                self.roster_size,
+            ),
+        ];
+        for (name, kind, help, value) in metrics {
+            writeln!(text, "# HELP vrc_doorkeeper_{} {}", name, help).unwrap();
+            writeln!(text, "# TYPE vrc_doorkeeper_{} {}", name, kind).unwrap();
+            writeln!(text, "vrc_doorkeeper_{} {}", name, value).unwrap();
+        }
+        text
+    }
+}
+
+// Serves `GET /metrics` in the background, e.g. on `127.0.0.1:9100`.
+pub fn spawn_metrics_server(addr: &str, metrics: Arc<Metrics>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &metrics));
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let (status, body) = match request_line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["GET", "/metrics"] => ("200 OK", metrics.snapshot().to_prometheus()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn metrics_count_events_by_name() {
+        let metrics = Metrics::default();
+        metrics.record_event(&Event::OnPlayerJoined {
+            user_name: "Alice".to_owned(),
+        });
+        metrics.record_event(&Event::OnPlayerJoined {
+            user_name: "Bob".to_owned(),
+        });
+        metrics.record_event(&Event::OnLeftRoom);
+        metrics.record_send(true);
+        metrics.record_send(false);
+        metrics.record_send(true);
+        metrics.set_roster_size(2);
+        let snapshot = metrics.snapshot();
+        let count = |name| snapshot.events.iter().find(|(n, _)| *n == name).unwrap().1;
+        assert_eq!(2, count("OnPlayerJoined"));
+        assert_eq!(1, count("OnLeftRoom"));
+        assert_eq!(0, count("OnJoinedRoom"));
+        assert_eq!(2, snapshot.notifications_sent);
+        assert_eq!(1, snapshot.send_failures);
+        assert_eq!(2, snapshot.roster_size);
+    }
+
+    #[test]
+    fn snapshot_renders_prometheus_text() {
+        let snapshot = MetricsSnapshot {
+            events: vec![("OnPlayerJoined", 3), ("OnPlayerLeft", 1)],
+            notifications_sent: 4,
+            send_failures: 1,
+            roster_size: 2,
+        };
+        let expected = "\
+# HELP vrc_doorkeeper_events_total Events parsed from the log.
+# TYPE vrc_doorkeeper_events_total counter
+vrc_doorkeeper_events_total{event=\"OnPlayerJoined\"} 3
+vrc_doorkeeper_events_total{event=\"OnPlayerLeft\"} 1
+# HELP vrc_doorkeeper_notifications_sent_total Notifications sent.
+# TYPE vrc_doorkeeper_notifications_sent_total counter
+vrc_doorkeeper_notifications_sent_total 4
+# HELP vrc_doorkeeper_send_failures_total Notifications failed to send.
+# TYPE vrc_doorkeeper_send_failures_total counter
+vrc_doorkeeper_send_failures_total 1
+# HELP vrc_doorkeeper_roster_size Players currently in the instance.
+# TYPE vrc_doorkeeper_roster_size gauge
+vrc_doorkeeper_roster_size 2
+";
+        assert_eq!(expected, snapshot.to_prometheus());
+    }
+
+    #[test]
+    fn metrics_server_serves_metrics() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.set_roster_size(5);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        spawn_metrics_server(&addr.to_string(), Arc::clone(&metrics)).unwrap();
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = get("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&metrics.snapshot().to_prometheus()));
+        assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
@@ -0,0 +1,3097 @@
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Local, NaiveTime, Timelike, Utc};
+
+use crate::assets::{IconPaths, IconSet};
+use crate::metrics::Metrics;
+use crate::reader::LogLineProcessor;
+use crate::vrc;
+use crate::vrc::log::{LogLine, SharedCustomRules};
+use crate::xsoverlay;
+use crate::xsoverlay::{
+    MessageDefaults, MessageObjectBuilder, NotificationAudio, NotificationSink, SendMessageError,
+};
+
+pub struct NotifierSettings {
+    // Joins (and leaves) arriving within this window are summarized into one notification.
+    // None sends every event as soon as it arrives.
+    pub batch_window: Option<Duration>,
+    // A join cancelled by a leave of the same player within this window (or vice versa)
+    // is not notified, e.g. when the connection of the player flapped.
+    pub flap_window: Option<Duration>,
+    // A join followed by a leave of the same player within this window is notified
+    // as one pass-through, e.g. an accidental join, and the join alone if the player stays.
+    // The flap window takes precedence, which notifies neither of them.
+    pub pass_through_window: Option<Duration>,
+    // Leaves logged within this window before leaving the instance are not notified,
+    // as VRChat logs everyone leaving while tearing the instance down.
+    // The leaves are held back for the window, or the flap window if it is set.
+    pub teardown_window: Option<Duration>,
+    // Notifies the players joining and leaving, which is what the notifier is usually for.
+    pub notify_join: bool,
+    pub notify_leave: bool,
+    // Notifies that VRChat was closed.
+    pub notify_application_quit: bool,
+    // Warns that a player was kicked, banned, timed out or warned, including the local one.
+    pub notify_moderation: bool,
+    // Notifies the world entered once it has been loaded, e.g. `Entered The Great Pug.`
+    pub notify_world: bool,
+    // Hides the names of the players in the notifications, e.g. while streaming.
+    // The roster and the deduplication still use the real names.
+    pub anonymize_names: Option<Anonymization>,
+    pub join_audio: NotificationAudio,
+    pub leave_audio: NotificationAudio,
+    // No notification is sent within this range of the local time.
+    pub quiet_hours: Option<QuietHours>,
+    // Warns that friends trying to join can't get in.
+    pub notify_instance_full: bool,
+    // Appends the local time of the event to the content, e.g. `(23:41)`.
+    pub show_timestamp: bool,
+    pub timestamp_format: String,
+    // What every notification starts with, e.g. the volume and the source app.
+    // The timeout is the one of a join or leave, while the other events have theirs in timeouts.
+    pub message_defaults: MessageDefaults,
+    // How long the events other than joins and leaves stay, by how much attention they need.
+    pub timeouts: EventTimeouts,
+    // Summarizes the session in the world when leaving it.
+    pub notify_session_summary: bool,
+    // Notifies entering the instance again within this window after leaving it, e.g. after a crash.
+    pub rejoin_window: Option<Duration>,
+    // Warns that the instance was left without entering another world within this window,
+    // which is a disconnect rather than travelling to another world.
+    pub disconnect_window: Option<Duration>,
+    // Notifies friends coming online or going offline.
+    pub notify_friend_presence: bool,
+    // Confirms that a screenshot was saved, with its path.
+    pub notify_photo_taken: bool,
+    // Notifies the player who has become the instance master.
+    pub notify_master_changed: bool,
+    // Warns that a portal was dropped in the instance, with its destination.
+    pub notify_portal_dropped: bool,
+    // Warns that the download of a world or an avatar has stalled, e.g. before a failed load.
+    pub notify_download_stalled: bool,
+    // Grows the height of the notification with the length of the text so that it is not clipped.
+    pub auto_height: bool,
+    // Reports the joins and leaves of the last minute at this interval, while any happened.
+    pub rate_summary_interval: Option<Duration>,
+    // Confirms at this interval that the notifier is still running and the overlay is reachable,
+    // with the number of players in the instance.
+    pub heartbeat_interval: Option<Duration>,
+    // Appends how long the player was in the instance to a leave, e.g. `Alice left (was here 23 min).`
+    // Omitted for the players who were already there when reading started.
+    pub show_time_in_instance: bool,
+    // The icon files replacing the embedded ones, read once when the notifier is created.
+    pub icons: IconPaths,
+    // Prefixes joins and leaves with the name of the current world, e.g. `[The Great Pug] Alice joined.`
+    pub show_world_name: bool,
+    // Confirms the account logged in, e.g. `Logged in as Alice.`
+    pub notify_authenticated: bool,
+    // Also confirms the login found in the log when reading starts, which is usually the only one.
+    pub notify_authenticated_on_first_read: bool,
+    // How to tell that the players already in the world have been loaded after entering it.
+    pub load_gate: LoadGate,
+    // The templates of the notifications by the name of the custom rules.
+    // The events of a rule without a template are not notified.
+    pub custom_templates: HashMap<String, String>,
+    // Gives each notification the next index, going back to 0 at this value,
+    // so that rapid notifications are stacked. None sends every notification with the index 0.
+    pub index_limit: Option<i32>,
+    // Warns when the number of players logged by VRChat differs from the roster,
+    // e.g. after reading started in the middle of a session or lines were missed.
+    pub reconcile_roster: bool,
+    // The content of the notification sent on startup. None sends nothing.
+    pub welcome_message: Option<String>,
+    // An event logged again at the same second within this window is dropped,
+    // e.g. when the same line has been read twice.
+    pub dedup_window: Option<Duration>,
+}
+
+impl NotifierSettings {
+    fn notifies(&self, presence: Presence) -> bool {
+        match presence {
+            Presence::Joined => self.notify_join,
+            Presence::Left => self.notify_leave,
+        }
+    }
+
+    // Starts a notification from the message defaults,
+    // which the lifecycle notifications, e.g. the welcome, share with the events.
+    pub fn builder(&self, title: String) -> MessageObjectBuilder {
+        MessageObjectBuilder::with_defaults(title, &self.message_defaults)
+    }
+}
+
+impl Default for NotifierSettings {
+    fn default() -> Self {
+        NotifierSettings {
+            batch_window: None,
+            flap_window: None,
+            pass_through_window: None,
+            teardown_window: None,
+            notify_join: true,
+            notify_leave: true,
+            notify_application_quit: true,
+            notify_moderation: true,
+            notify_world: false,
+            anonymize_names: None,
+            join_audio: NotificationAudio::default(),
+            leave_audio: NotificationAudio::default(),
+            quiet_hours: None,
+            notify_instance_full: false,
+            show_timestamp: false,
+            timestamp_format: "%H:%M".to_owned(),
+            message_defaults: MessageDefaults {
+                timeout: 1f32,
+                ..Default::default()
+            },
+            timeouts: EventTimeouts::default(),
+            notify_session_summary: false,
+            rejoin_window: None,
+            disconnect_window: None,
+            notify_friend_presence: false,
+            notify_photo_taken: false,
+            notify_portal_dropped: false,
+            notify_download_stalled: false,
+            notify_master_changed: false,
+            auto_height: false,
+            rate_summary_interval: None,
+            heartbeat_interval: None,
+            show_time_in_instance: false,
+            icons: IconPaths::default(),
+            show_world_name: false,
+            notify_authenticated: false,
+            notify_authenticated_on_first_read: true,
+            load_gate: LoadGate::default(),
+            custom_templates: HashMap::new(),
+            index_limit: None,
+            reconcile_roster: false,
+            welcome_message: Some("Join and Leave notifications are enabled.".to_owned()),
+            dedup_window: None,
+        }
+    }
+}
+
+// The timeouts of the notifications in seconds, by the category of the event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EventTimeouts {
+    // e.g. a login, a screenshot or the new instance master.
+    pub info: f32,
+    // e.g. moderation, a dropped portal or a full instance.
+    pub warning: f32,
+}
+
+impl Default for EventTimeouts {
+    fn default() -> Self {
+        EventTimeouts {
+            info: 2f32,
+            warning: 3f32,
+        }
+    }
+}
+
+// Joins and leaves are not notified while the world is loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadGate {
+    // Loading ends after this time since entering or leaving a world.
+    Window(Duration),
+    // Loading ends when no one has joined for this time since entering or leaving a world,
+    // which follows slow and quick loads better than a fixed window.
+    Quiescence(Duration),
+}
+
+impl Default for LoadGate {
+    fn default() -> Self {
+        LoadGate::Window(Duration::seconds(5))
+    }
+}
+
+impl LoadGate {
+    fn period(&self) -> Duration {
+        match self {
+            LoadGate::Window(period) | LoadGate::Quiescence(period) => *period,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anonymization {
+    // The first character followed by asterisks, e.g. `A***`.
+    Masked,
+    // A hash which stays the same for the same name across runs, e.g. `Player 3f2a1c`.
+    Hashed,
+}
+
+impl Anonymization {
+    pub fn apply(&self, user_name: &str) -> String {
+        let user_name = user_name.trim();
+        match self {
+            Anonymization::Masked => match user_name.chars().next() {
+                Some(first) => format!("{}***", first),
+                None => "***".to_owned(),
+            },
+            Anonymization::Hashed => format!("Player {:06x}", fnv1a(user_name) & 0xff_ffff),
+        }
+    }
+}
+
+// FNV-1a, which unlike the hasher of std does not change between Rust versions.
+fn fnv1a(text: &str) -> u32 {
+    text.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    // The range includes start and excludes end, and wraps past midnight if end is before start.
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Presence {
+    Joined,
+    Left,
+}
+
+impl Presence {
+    fn of(event: &vrc::Event) -> Option<(Presence, &str)> {
+        match event {
+            vrc::Event::OnPlayerJoined { user_name } => Some((Presence::Joined, user_name)),
+            vrc::Event::OnPlayerLeft { user_name } => Some((Presence::Left, user_name)),
+            _ => None,
+        }
+    }
+
+    fn opposite(&self) -> Presence {
+        match self {
+            Presence::Joined => Presence::Left,
+            Presence::Left => Presence::Joined,
+        }
+    }
+
+    fn icon<'a>(&self, icons: &'a IconSet) -> &'a str {
+        match self {
+            Presence::Joined => &icons.joined,
+            Presence::Left => &icons.left,
+        }
+    }
+
+    fn verb(&self) -> &'static str {
+        match self {
+            Presence::Joined => "joined",
+            Presence::Left => "left",
+        }
+    }
+}
+
+// Players currently in the instance with the time each of them was seen joining,
+// which is unknown for the ones already there when reading started.
+#[derive(Debug, Default)]
+pub struct Roster {
+    joined_at: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+impl Roster {
+    pub fn len(&self) -> usize {
+        self.joined_at.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.joined_at.is_empty()
+    }
+
+    pub fn contains(&self, user_name: &str) -> bool {
+        self.joined_at.contains_key(user_name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.joined_at.keys().map(String::as_str)
+    }
+
+    // None if the player is not there or the join time is unknown.
+    pub fn time_in_instance(&self, user_name: &str, now: DateTime<Utc>) -> Option<Duration> {
+        let joined_at = (*self.joined_at.get(user_name)?)?;
+        Some(now - joined_at)
+    }
+
+    // A join read again keeps the first time.
+    fn join(&mut self, user_name: &str, joined_at: Option<DateTime<Utc>>) {
+        self.joined_at
+            .entry(user_name.to_owned())
+            .or_insert(joined_at);
+    }
+
+    // Returns how long the player was there, if known.
+    fn leave(&mut self, user_name: &str, now: DateTime<Utc>) -> Option<Duration> {
+        let stay = self.time_in_instance(user_name, now);
+        self.joined_at.remove(user_name);
+        stay
+    }
+
+    fn clear(&mut self) {
+        self.joined_at.clear();
+    }
+}
+
+// e.g. `42 s` or `23 min`.
+fn format_stay(stay: Duration) -> String {
+    if stay < Duration::minutes(1) {
+        format!("{} s", stay.num_seconds().max(0))
+    } else {
+        format!("{} min", stay.num_minutes())
+    }
+}
+
+// Counts of a session in a world, from OnJoinedRoom to OnLeftRoom.
+struct SessionStats {
+    started_at: DateTime<Utc>,
+    seen: HashSet<String>,
+    joins: u32,
+    leaves: u32,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SessionSummary {
+    pub players_seen: usize,
+    pub joins: u32,
+    pub leaves: u32,
+    pub duration: Duration,
+}
+
+impl SessionStats {
+    fn new(started_at: DateTime<Utc>) -> Self {
+        SessionStats {
+            started_at,
+            seen: HashSet::new(),
+            joins: 0,
+            leaves: 0,
+        }
+    }
+
+    fn record(&mut self, event: &vrc::Event) {
+        match event {
+            vrc::Event::OnPlayerJoined { user_name } => {
+                self.seen.insert(user_name.to_owned());
+                self.joins += 1;
+            }
+            vrc::Event::OnPlayerLeft { .. } => self.leaves += 1,
+            _ => (),
+        }
+    }
+
+    fn summary(&self, now: DateTime<Utc>) -> SessionSummary {
+        SessionSummary {
+            players_seen: self.seen.len(),
+            joins: self.joins,
+            leaves: self.leaves,
+            duration: now - self.started_at,
+        }
+    }
+}
+
+impl SessionSummary {
+    fn to_notification(&self, defaults: &MessageDefaults) -> MessageObjectBuilder {
+        let title = format!(
+            "Session ended: {} players seen over {} min.",
+            self.players_seen,
+            self.duration.num_minutes()
+        );
+        MessageObjectBuilder::with_defaults(title, defaults)
+            .set_content(format!("{} joins, {} leaves", self.joins, self.leaves))
+            .set_timeout(3f32)
+    }
+}
+
+// Joins and leaves within the last minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresenceRate {
+    pub joins_per_minute: usize,
+    pub leaves_per_minute: usize,
+}
+
+impl PresenceRate {
+    fn to_notification(self, defaults: &MessageDefaults) -> MessageObjectBuilder {
+        MessageObjectBuilder::with_defaults(
+            format!("{} joins/min", self.joins_per_minute),
+            defaults,
+        )
+        .set_content(format!("{} leaves/min", self.leaves_per_minute))
+        .set_timeout(2f32)
+    }
+}
+
+// The times of the recent joins and leaves, oldest first.
+#[derive(Default)]
+struct PresenceRateCounter {
+    joins: VecDeque<DateTime<Utc>>,
+    leaves: VecDeque<DateTime<Utc>>,
+}
+
+impl PresenceRateCounter {
+    fn window() -> Duration {
+        Duration::seconds(60)
+    }
+
+    fn record(&mut self, presence: Presence, now: DateTime<Utc>) {
+        let times = match presence {
+            Presence::Joined => &mut self.joins,
+            Presence::Left => &mut self.leaves,
+        };
+        times.push_back(now);
+        self.expire(now);
+    }
+
+    fn expire(&mut self, now: DateTime<Utc>) {
+        for times in [&mut self.joins, &mut self.leaves] {
+            while times.front().is_some_and(|&t| now - t >= Self::window()) {
+                times.pop_front();
+            }
+        }
+    }
+
+    fn rate(&self, now: DateTime<Utc>) -> PresenceRate {
+        let count = |times: &VecDeque<DateTime<Utc>>| {
+            times.iter().filter(|&&t| now - t < Self::window()).count()
+        };
+        PresenceRate {
+            joins_per_minute: count(&self.joins),
+            leaves_per_minute: count(&self.leaves),
+        }
+    }
+}
+
+// The events seen lately with the times logged and received, oldest first.
+#[derive(Default)]
+struct RecentEvents {
+    entries: VecDeque<(vrc::Event, DateTime<Local>, DateTime<Utc>)>,
+}
+
+impl RecentEvents {
+    // Up to this many events are remembered, however short the window is.
+    const CAPACITY: usize = 64;
+
+    // Returns whether the same event at the same second has been seen within the window,
+    // remembering the event otherwise.
+    fn is_duplicate(
+        &mut self,
+        event: &vrc::Event,
+        time: DateTime<Local>,
+        now: DateTime<Utc>,
+        window: Duration,
+    ) -> bool {
+        while self
+            .entries
+            .front()
+            .is_some_and(|(_, _, seen_at)| now - *seen_at >= window)
+        {
+            self.entries.pop_front();
+        }
+        let time = time.with_nanosecond(0).unwrap_or(time);
+        if self
+            .entries
+            .iter()
+            .any(|(e, t, _)| e == event && *t == time)
+        {
+            return true;
+        }
+        if self.entries.len() == Self::CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((event.clone(), time, now));
+        false
+    }
+}
+
+// The instance entered last and the time of the last line while staying there.
+struct LastInstance {
+    instance_id: String,
+    seen_at: DateTime<Local>,
+    is_inside: bool,
+}
+
+// A join or leave waiting to see whether it is cancelled by the opposite one.
+struct HeldPresence {
+    presence: Presence,
+    user_name: String,
+    time: DateTime<Local>,
+    // How long the player was in the instance, for a leave.
+    stay: Option<Duration>,
+    release_at: DateTime<Utc>,
+}
+
+// Players who joined or left since the first one of the batch arrived.
+struct PendingBatch {
+    user_names: Vec<String>,
+    // the time of the latest event in the batch.
+    time: DateTime<Local>,
+    started_at: DateTime<Utc>,
+}
+
+pub struct VrcToXsOverlayNotifier<C, S>
+where
+    C: CurrentTimeProvider,
+    S: NotificationSink,
+{
+    client: S,
+    settings: NotifierSettings,
+    // The last time of OnJoinedRoom or OnLeftRoom detected.
+    // At the end of DST, the time provided from log file may be ambiguous.
+    // so this field must be assigned with current system time.
+    // This field is used to determine whether the join or left event is not caused by moving world.
+    notifiable_since: Option<DateTime<Utc>>,
+    held: VecDeque<HeldPresence>,
+    pending_joins: Option<PendingBatch>,
+    pending_leaves: Option<PendingBatch>,
+    // Players currently in the instance, including the ones already there when reading started.
+    roster: Roster,
+    // None until entering a world after reading started.
+    session: Option<SessionStats>,
+    // Shared with the control listener to mute the notifications at runtime.
+    muted: Arc<AtomicBool>,
+    // Shared with the metrics server.
+    metrics: Arc<Metrics>,
+    last_instance: Option<LastInstance>,
+    // The world entered last, until leaving it.
+    world_name: Option<String>,
+    // The time of OnLeftRoom, until entering another world.
+    left_room_at: Option<DateTime<Utc>>,
+    presence_rate: PresenceRateCounter,
+    next_rate_summary_at: Option<DateTime<Utc>>,
+    next_heartbeat_at: Option<DateTime<Utc>>,
+    // Also receives the joins and leaves, but nothing else, e.g. the chatbox which others can read.
+    presence_sink: Option<Box<dyn NotificationSink>>,
+    // Called when the client fails to send a notification.
+    error_handler: Box<dyn Fn(&SendMessageError)>,
+    // Their templates take precedence over custom_templates of the settings.
+    custom_rules: SharedCustomRules,
+    recent_events: RecentEvents,
+    // The index of the next notification sent.
+    next_index: Cell<i32>,
+    icons: IconSet,
+    current_time_provider: C,
+}
+
+impl<C: CurrentTimeProvider, S: NotificationSink> VrcToXsOverlayNotifier<C, S> {
+    pub fn new(client: S, current_time_provider: C, settings: NotifierSettings) -> Self {
+        let icons = IconSet::load(&settings.icons);
+        VrcToXsOverlayNotifier {
+            client,
+            settings,
+            notifiable_since: None,
+            held: VecDeque::new(),
+            pending_joins: None,
+            pending_leaves: None,
+            roster: Roster::default(),
+            session: None,
+            muted: Arc::new(AtomicBool::new(false)),
+            metrics: Arc::new(Metrics::default()),
+            last_instance: None,
+            world_name: None,
+            left_room_at: None,
+            presence_rate: PresenceRateCounter::default(),
+            next_rate_summary_at: None,
+            next_heartbeat_at: None,
+            presence_sink: None,
+            error_handler: Box::new(|e| eprintln!("{}", e)),
+            custom_rules: SharedCustomRules::default(),
+            recent_events: RecentEvents::default(),
+            next_index: Cell::new(0),
+            icons,
+            current_time_provider,
+        }
+    }
+
+    // Renders the custom events with the templates of the rules, which may be replaced while running.
+    pub fn set_custom_rules(&mut self, custom_rules: SharedCustomRules) {
+        self.custom_rules = custom_rules;
+    }
+
+    pub fn set_presence_sink<P: NotificationSink + 'static>(&mut self, presence_sink: P) {
+        self.presence_sink = Some(Box::new(presence_sink));
+    }
+
+    // Replaces printing the error to stderr, e.g. to show it in the UI of an embedder.
+    pub fn set_error_handler<F>(&mut self, error_handler: F)
+    where
+        F: Fn(&SendMessageError) + 'static,
+    {
+        self.error_handler = Box::new(error_handler);
+    }
+
+    pub fn current_roster(&self) -> &Roster {
+        &self.roster
+    }
+
+    pub fn muted_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.muted)
+    }
+
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    pub fn session_summary(&self) -> Option<SessionSummary> {
+        let now = self.current_time_provider.current_time();
+        self.session.as_ref().map(|session| session.summary(now))
+    }
+
+    // Counted at the time each join or leave is read, not the time in the log.
+    pub fn presence_rate(&self) -> PresenceRate {
+        let now = self.current_time_provider.current_time();
+        self.presence_rate.rate(now)
+    }
+
+    // Returns how long the player who left was in the instance, if known.
+    fn update_roster(&mut self, event: &vrc::Event, is_first: bool) -> Option<Duration> {
+        let now = self.current_time_provider.current_time();
+        match event {
+            vrc::Event::OnJoinedRoom | vrc::Event::OnLeftRoom => self.roster.clear(),
+            vrc::Event::OnPlayerJoined { user_name } => {
+                // the join was logged before reading started, at an unknown time.
+                let joined_at = if is_first { None } else { Some(now) };
+                self.roster.join(user_name, joined_at);
+            }
+            // a leave of someone never seen joining is simply ignored.
+            vrc::Event::OnPlayerLeft { user_name } => return self.roster.leave(user_name, now),
+            _ => (),
+        }
+        None
+    }
+
+    // Returns whether the line enters the instance left within the rejoin window.
+    // The times are taken from the log so that the instance left before reading started counts.
+    fn track_instance(&mut self, line: &LogLine) -> bool {
+        match &line.event {
+            Some(vrc::Event::InstanceInfo { instance_id, .. }) => {
+                let rejoined = match (&self.last_instance, self.settings.rejoin_window) {
+                    (Some(last), Some(window)) => {
+                        last.instance_id == *instance_id && line.time - last.seen_at <= window
+                    }
+                    _ => false,
+                };
+                self.last_instance = Some(LastInstance {
+                    instance_id: instance_id.to_owned(),
+                    seen_at: line.time,
+                    is_inside: true,
+                });
+                rejoined
+            }
+            event => {
+                if let Some(last) = self.last_instance.as_mut().filter(|last| last.is_inside) {
+                    last.seen_at = line.time;
+                    // a new login is a new session even if VRChat crashed without leaving.
+                    if let Some(
+                        vrc::Event::OnLeftRoom
+                        | vrc::Event::ApplicationQuit
+                        | vrc::Event::UserAuthenticated { .. },
+                    ) = event
+                    {
+                        last.is_inside = false;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    // Muted or within quiet hours.
+    fn is_silenced(&self) -> bool {
+        if self.muted.load(Ordering::SeqCst) {
+            return true;
+        }
+        let now = self.current_time_provider.current_time();
+        match self.settings.quiet_hours {
+            Some(quiet_hours) => quiet_hours.contains(now.with_timezone(&Local).time()),
+            None => false,
+        }
+    }
+
+    fn is_notifiable(&self) -> bool {
+        !self.is_silenced() && !self.is_loading()
+    }
+
+    fn is_loading(&self) -> bool {
+        let now = self.current_time_provider.current_time();
+        match self.notifiable_since {
+            Some(notifiable_since) => now < notifiable_since,
+            None => false,
+        }
+    }
+
+    fn to_notification_object(&self, line: vrc::log::LogLine) -> Option<xsoverlay::MessageObject> {
+        if !self.is_notifiable() {
+            return None;
+        }
+
+        let message = match line.event? {
+            vrc::Event::OnPlayerJoined { user_name } if self.settings.notify_join => {
+                self.presence_notification(Presence::Joined, &[user_name], None)
+            }
+            vrc::Event::OnPlayerLeft { user_name } if self.settings.notify_leave => {
+                self.presence_notification(Presence::Left, &[user_name], None)
+            }
+            vrc::Event::ApplicationQuit if self.settings.notify_application_quit => {
+                self.builder("VRChat closed.".to_owned()).set_timeout(1f32)
+            }
+            vrc::Event::UserAuthenticated { user_name } if self.settings.notify_authenticated => {
+                self.builder(format!("Logged in as {}.", self.display_name(&user_name)))
+                    .set_timeout(self.settings.timeouts.info)
+            }
+            vrc::Event::InstanceFull if self.settings.notify_instance_full => self
+                .warning_builder("The instance is full.".to_owned())
+                .set_content("Friends trying to join can't get in.".to_owned())
+                .set_timeout(self.settings.timeouts.warning),
+            vrc::Event::PlayerCount { count }
+                if self.settings.reconcile_roster && count != self.roster.len() =>
+            {
+                self.warning_builder("Player count mismatch.".to_owned())
+                    .set_content(format!(
+                        "{} in the instance, but {} known.",
+                        count,
+                        self.roster.len()
+                    ))
+                    .set_timeout(self.settings.timeouts.warning)
+            }
+            vrc::Event::FriendPresence { user_name, online }
+                if self.settings.notify_friend_presence =>
+            {
+                let state = if online { "online" } else { "offline" };
+                self.builder(format!("{} is {}.", self.display_name(&user_name), state))
+                    .set_timeout(self.settings.timeouts.info)
+            }
+            vrc::Event::PhotoTaken { path } if self.settings.notify_photo_taken => self
+                .builder("Screenshot saved.".to_owned())
+                .set_content(path)
+                .set_timeout(self.settings.timeouts.info),
+            vrc::Event::MasterChanged { user_name } if self.settings.notify_master_changed => self
+                .builder(format!(
+                    "{} is now the instance master.",
+                    self.display_name(&user_name)
+                ))
+                .set_timeout(self.settings.timeouts.info),
+            vrc::Event::PortalDropped { user_name, world }
+                if self.settings.notify_portal_dropped =>
+            {
+                let title = match user_name {
+                    Some(user_name) => {
+                        format!("{} dropped a portal.", self.display_name(&user_name))
+                    }
+                    None => "A portal was dropped.".to_owned(),
+                };
+                self.warning_builder(title)
+                    .set_content(world.unwrap_or_default())
+                    .set_timeout(self.settings.timeouts.warning)
+            }
+            vrc::Event::AssetDownloadStalled { what } if self.settings.notify_download_stalled => {
+                self.warning_builder("Download stalled.".to_owned())
+                    .set_content(what)
+                    .set_timeout(self.settings.timeouts.warning)
+            }
+            vrc::Event::Custom { name, captures } => {
+                let template = match self.custom_rules.template(&name) {
+                    Some(template) => template,
+                    None => self.settings.custom_templates.get(&name)?.to_owned(),
+                };
+                self.builder(vrc::log::render_template(&template, &captures))
+                    .set_timeout(self.settings.timeouts.info)
+            }
+            vrc::Event::Moderation { kind, target } if self.settings.notify_moderation => {
+                let action = match kind {
+                    vrc::ModerationKind::Kick => "kicked".to_owned(),
+                    vrc::ModerationKind::Ban => "banned".to_owned(),
+                    vrc::ModerationKind::Timeout => "timed out".to_owned(),
+                    vrc::ModerationKind::Warn => "warned".to_owned(),
+                    vrc::ModerationKind::Custom(action) => action,
+                };
+                let title = match target {
+                    Some(target) => format!("{} was {}.", self.display_name(&target), action),
+                    None => format!("You were {}.", action),
+                };
+                self.warning_builder(title)
+                    .set_timeout(self.settings.timeouts.warning)
+            }
+            _ => return None,
+        };
+        Some(self.finish(message, &line.time))
+    }
+
+    fn presence_notification(
+        &self,
+        presence: Presence,
+        user_names: &[String],
+        stay: Option<Duration>,
+    ) -> MessageObjectBuilder {
+        let user_names: Vec<_> = user_names
+            .iter()
+            .map(|user_name| self.display_name(user_name))
+            .collect();
+        let title = match stay {
+            Some(stay) => format!(
+                "{} {} (was here {}).",
+                summarize_user_names(&user_names),
+                presence.verb(),
+                format_stay(stay)
+            ),
+            None => format!("{} {}.", summarize_user_names(&user_names), presence.verb()),
+        };
+        self.presence_builder(presence, title)
+    }
+
+    // The player left soon after joining, which is notified as a leave.
+    fn pass_through_notification(&self, user_name: &str) -> MessageObjectBuilder {
+        let title = format!("{} passed through.", self.display_name(user_name));
+        self.presence_builder(Presence::Left, title)
+    }
+
+    // The name as shown in the notifications, while the roster keeps the real one.
+    fn display_name(&self, user_name: &str) -> String {
+        match self.settings.anonymize_names {
+            Some(anonymization) => anonymization.apply(user_name),
+            None => user_name.to_owned(),
+        }
+    }
+
+    fn presence_builder(&self, presence: Presence, title: String) -> MessageObjectBuilder {
+        let mut title = title;
+        if let Some(world_name) = self
+            .world_name
+            .as_ref()
+            .filter(|_| self.settings.show_world_name)
+        {
+            title = format!("[{}] {}", world_name, title);
+        }
+        let audio = match presence {
+            Presence::Joined => &self.settings.join_audio,
+            Presence::Left => &self.settings.leave_audio,
+        };
+        self.builder(title)
+            .set_icon(
+                xsoverlay::NotificationType::Custom(String::from(presence.icon(&self.icons))),
+                true,
+            )
+            .set_audio(audio.clone())
+    }
+
+    fn builder(&self, title: String) -> MessageObjectBuilder {
+        self.settings.builder(title)
+    }
+
+    fn warning_builder(&self, title: String) -> MessageObjectBuilder {
+        let builder = self.builder(title);
+        match &self.icons.warning {
+            Some(icon) => builder.set_icon(xsoverlay::NotificationType::Custom(icon.clone()), true),
+            None => builder.set_icon(xsoverlay::NotificationType::Warning, false),
+        }
+    }
+
+    fn finish(
+        &self,
+        builder: MessageObjectBuilder,
+        time: &DateTime<Local>,
+    ) -> xsoverlay::MessageObject {
+        let mut builder = builder;
+        if self.settings.show_timestamp {
+            let timestamp = format!("({})", time.format(&self.settings.timestamp_format));
+            builder = builder.append_content(&timestamp);
+        }
+        let message = builder.build();
+        if !self.settings.auto_height {
+            return message;
+        }
+        let height = notification_height(message.title(), message.content());
+        MessageObjectBuilder::from(message)
+            .set_height(height)
+            .build()
+    }
+
+    // Holds the join or leave back until the flap window elapses,
+    // or only the join until the pass-through window elapses.
+    fn hold_presence(
+        &mut self,
+        presence: Presence,
+        user_name: &str,
+        time: DateTime<Local>,
+        stay: Option<Duration>,
+    ) {
+        let opposite = self
+            .held
+            .iter()
+            .position(|held| held.presence == presence.opposite() && held.user_name == user_name);
+        if let Some(index) = opposite {
+            let held = self.held.remove(index).unwrap();
+            match held.presence {
+                // neither of them is worth notifying within the flap window.
+                _ if self.settings.flap_window.is_some() => return,
+                Presence::Joined => {
+                    let builder = self.pass_through_notification(user_name);
+                    self.send_presence(&self.finish(builder, &time));
+                    return;
+                }
+                // a leave held only for the teardown window really happened,
+                // so it is sent ahead of the rejoin, not after it.
+                Presence::Left => {
+                    self.deliver_presence(held.presence, held.user_name, held.time, held.stay)
+                }
+            }
+        }
+        let hold_window = match presence {
+            Presence::Joined => self.settings.pass_through_window,
+            Presence::Left => self.settings.teardown_window,
+        };
+        let window = match self.settings.flap_window.or(hold_window) {
+            Some(window) => window,
+            None => return self.deliver_presence(presence, user_name.to_owned(), time, stay),
+        };
+        self.held.push_back(HeldPresence {
+            presence,
+            user_name: user_name.to_owned(),
+            time,
+            stay,
+            release_at: self.current_time_provider.current_time() + window,
+        });
+    }
+
+    // Drops the leaves held back within the teardown window before leaving the instance.
+    fn discard_teardown_leaves(&mut self, left_at: DateTime<Local>) {
+        if let Some(window) = self.settings.teardown_window {
+            self.held
+                .retain(|held| held.presence != Presence::Left || left_at - held.time > window);
+        }
+    }
+
+    // Delivers the held events whose window has elapsed, or all of them if force is set.
+    fn release_held(&mut self, force: bool) {
+        let now = self.current_time_provider.current_time();
+        while let Some(held) = self.held.front() {
+            if !force && now < held.release_at {
+                break;
+            }
+            let held = self.held.pop_front().unwrap();
+            self.deliver_presence(held.presence, held.user_name, held.time, held.stay);
+        }
+    }
+
+    // Sends the join or leave, or adds it to the batch if batching is enabled.
+    // The stay is left out of a batch, which has several players.
+    fn deliver_presence(
+        &mut self,
+        presence: Presence,
+        user_name: String,
+        time: DateTime<Local>,
+        stay: Option<Duration>,
+    ) {
+        if self.settings.batch_window.is_none() {
+            let builder = self.presence_notification(presence, &[user_name], stay);
+            self.send_presence(&self.finish(builder, &time));
+            return;
+        }
+        let now = self.current_time_provider.current_time();
+        let pending = match presence {
+            Presence::Joined => &mut self.pending_joins,
+            Presence::Left => &mut self.pending_leaves,
+        };
+        let batch = pending.get_or_insert_with(|| PendingBatch {
+            user_names: Vec::new(),
+            time,
+            started_at: now,
+        });
+        batch.user_names.push(user_name);
+        batch.time = time;
+    }
+
+    // Sends the batches whose window has elapsed, or all of them if force is set.
+    fn flush_batches(&mut self, force: bool) {
+        let window = match self.settings.batch_window {
+            Some(window) => window,
+            None => return,
+        };
+        let now = self.current_time_provider.current_time();
+        for presence in [Presence::Joined, Presence::Left] {
+            let pending = match presence {
+                Presence::Joined => &mut self.pending_joins,
+                Presence::Left => &mut self.pending_leaves,
+            };
+            let is_due = match pending {
+                Some(batch) => force || now >= batch.started_at + window,
+                None => false,
+            };
+            if is_due {
+                let batch = pending.take().unwrap();
+                let builder = self.presence_notification(presence, &batch.user_names, None);
+                self.send_presence(&self.finish(builder, &batch.time));
+            }
+        }
+    }
+
+    fn send_session_summary(&self, time: &DateTime<Local>) {
+        if !self.settings.notify_session_summary || !self.is_notifiable() {
+            return;
+        }
+        if let Some(summary) = self.session_summary() {
+            self.send(&self.finish(
+                summary.to_notification(&self.settings.message_defaults),
+                time,
+            ));
+        }
+    }
+
+    fn send_rate_summary(&mut self) {
+        let interval = match self.settings.rate_summary_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = self.current_time_provider.current_time();
+        let next = *self.next_rate_summary_at.get_or_insert(now + interval);
+        if now < next {
+            return;
+        }
+        self.next_rate_summary_at = Some(now + interval);
+        self.presence_rate.expire(now);
+        let rate = self.presence_rate.rate(now);
+        if rate.joins_per_minute == 0 && rate.leaves_per_minute == 0 || self.is_silenced() {
+            return;
+        }
+        self.send(&self.finish(
+            rate.to_notification(&self.settings.message_defaults),
+            &now.with_timezone(&Local),
+        ));
+    }
+
+    // Travelling to another world enters it soon after leaving the previous one.
+    fn track_departure(&mut self, event: &vrc::Event) {
+        match event {
+            vrc::Event::OnLeftRoom => {
+                self.left_room_at = Some(self.current_time_provider.current_time());
+            }
+            vrc::Event::EnteringRoom { .. }
+            | vrc::Event::OnJoinedRoom
+            | vrc::Event::ApplicationQuit => self.left_room_at = None,
+            _ => (),
+        }
+    }
+
+    // Sent as the world is still loading, so this is not gated by notifiable_since.
+    fn send_world_entered(&self, time: &DateTime<Local>) {
+        if !self.settings.notify_world || self.is_silenced() {
+            return;
+        }
+        let title = match &self.world_name {
+            Some(world_name) => format!("Entered {}.", world_name),
+            None => "Entered a world.".to_owned(),
+        };
+        let builder = self.builder(title).set_timeout(self.settings.timeouts.info);
+        self.send(&self.finish(builder, time));
+    }
+
+    fn check_disconnect(&mut self) {
+        let window = match self.settings.disconnect_window {
+            Some(window) => window,
+            None => return,
+        };
+        let now = self.current_time_provider.current_time();
+        if self
+            .left_room_at
+            .is_none_or(|left_at| now - left_at < window)
+        {
+            return;
+        }
+        self.left_room_at = None;
+        if self.is_silenced() {
+            return;
+        }
+        let builder = self
+            .warning_builder("Disconnected from the instance.".to_owned())
+            .set_content("No world was entered after leaving it.".to_owned())
+            .set_timeout(self.settings.timeouts.warning);
+        self.send(&self.finish(builder, &now.with_timezone(&Local)));
+    }
+
+    fn send_heartbeat(&mut self) {
+        let interval = match self.settings.heartbeat_interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let now = self.current_time_provider.current_time();
+        let next = *self.next_heartbeat_at.get_or_insert(now + interval);
+        if now < next {
+            return;
+        }
+        self.next_heartbeat_at = Some(now + interval);
+        if self.is_silenced() {
+            return;
+        }
+        let builder = self
+            .builder(format!(
+                "Still watching — {} players in the instance.",
+                self.roster.len()
+            ))
+            .set_timeout(self.settings.timeouts.info);
+        self.send(&self.finish(builder, &now.with_timezone(&Local)));
+    }
+
+    fn send(&self, message: &xsoverlay::MessageObject) {
+        let result = match self.settings.index_limit {
+            Some(limit) => {
+                let index = self.next_index.get();
+                self.next_index.set((index + 1) % limit.max(1));
+                let message = MessageObjectBuilder::from(message.clone())
+                    .set_index(index)
+                    .build();
+                self.client.send_message(&message)
+            }
+            None => self.client.send_message(message),
+        };
+        self.metrics.record_send(result.is_ok());
+        if let Err(e) = result {
+            (self.error_handler)(&e);
+        }
+    }
+
+    fn send_presence(&self, message: &xsoverlay::MessageObject) {
+        self.send(message);
+        if let Some(Err(e)) = self.presence_sink.as_ref().map(|s| s.send_message(message)) {
+            (self.error_handler)(&e);
+        }
+    }
+}
+
+// Lists up to three names, e.g. "Alice, Bob and Carol" or "Alice, Bob and 4 others".
+fn summarize_user_names(user_names: &[String]) -> String {
+    match user_names {
+        [] => String::new(),
+        [name] => name.to_owned(),
+        [names @ .., last] if user_names.len() <= 3 => format!("{} and {}", names.join(", "), last),
+        [first, second, rest @ ..] => format!("{}, {} and {} others", first, second, rest.len()),
+    }
+}
+
+const BASE_HEIGHT: f32 = 175f32;
+const LINE_HEIGHT: f32 = 25f32;
+const CHARS_PER_LINE: usize = 32;
+
+// The default height fits a line of title and a line of content.
+// Each line past them adds LINE_HEIGHT, wrapping at CHARS_PER_LINE characters.
+fn notification_height(title: &str, content: &str) -> f32 {
+    let lines = |text: &str| {
+        text.lines()
+            .map(|line| line.chars().count().div_ceil(CHARS_PER_LINE).max(1))
+            .sum::<usize>()
+    };
+    let extra_lines = lines(title).saturating_sub(1) + lines(content).saturating_sub(1);
+    BASE_HEIGHT + LINE_HEIGHT * extra_lines as f32
+}
+
+pub trait CurrentTimeProvider {
+    fn current_time(&self) -> DateTime<Utc>;
+}
+
+// Tells that the notifications are enabled, unless disabled by the settings.
+// A failure is only reported, e.g. when the overlay has not been launched yet,
+// as the later notifications try the overlay again.
+// Returns whether the welcome has been sent.
+pub fn send_welcome<S: NotificationSink + ?Sized>(client: &S, settings: &NotifierSettings) -> bool {
+    let content = match &settings.welcome_message {
+        Some(content) => content,
+        None => return false,
+    };
+    let welcome = settings
+        .builder("VRC Doorkeeper".to_owned())
+        .set_content(content.to_owned())
+        .set_timeout(2f32)
+        .build();
+    match client.send_message(&welcome) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to send the welcome: {}", e);
+            false
+        }
+    }
+}
+
+pub struct DefaultCurrentTimeProvider {}
+
+impl CurrentTimeProvider for DefaultCurrentTimeProvider {
+    fn current_time(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl<C: CurrentTimeProvider, S: NotificationSink> LogLineProcessor
+    for VrcToXsOverlayNotifier<C, S>
+{
+    // Every line is needed, not only the events, as its time tells that the instance is still open.
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        if let Some(event) = &line.event {
+            self.metrics.record_event(event);
+        }
+        if let (Some(event), Some(window)) = (&line.event, self.settings.dedup_window) {
+            let now = self.current_time_provider.current_time();
+            if self
+                .recent_events
+                .is_duplicate(event, line.time, now, window)
+            {
+                return;
+            }
+        }
+        let mut stay = None;
+        if let Some(event) = &line.event {
+            stay = self.update_roster(event, is_first);
+            self.metrics.set_roster_size(self.roster.len());
+            if let vrc::Event::EnteringRoom { world_name } = event {
+                self.world_name = Some(world_name.to_owned());
+            }
+        }
+        let rejoined = self.track_instance(&line);
+
+        if is_first {
+            // do not send any notification but the login.
+            let is_authenticated = matches!(line.event, Some(vrc::Event::UserAuthenticated { .. }));
+            if is_authenticated && self.settings.notify_authenticated_on_first_read {
+                if let Some(message) = self.to_notification_object(line) {
+                    self.send(&message);
+                }
+            }
+            return;
+        }
+        // a new event must not be merged into a batch which has already expired.
+        self.release_held(false);
+        self.flush_batches(false);
+        // entering a world after the window is still a disconnect.
+        self.check_disconnect();
+        if let Some(event) = &line.event {
+            self.track_departure(event);
+        }
+
+        // the world is still loading, so this is not gated by notifiable_since.
+        if rejoined && !self.is_silenced() {
+            let mut builder = self
+                .builder("Rejoined the same instance.".to_owned())
+                .set_timeout(self.settings.timeouts.info);
+            if let Some(vrc::Event::InstanceInfo {
+                region: Some(region),
+                ..
+            }) = &line.event
+            {
+                builder = builder.set_content(region.to_string());
+            }
+            self.send(&self.finish(builder, &line.time));
+        }
+
+        if let Some(event) = &line.event {
+            if let Some(session) = &mut self.session {
+                session.record(event);
+            }
+            if let Some((presence, _)) = Presence::of(event) {
+                let now = self.current_time_provider.current_time();
+                self.presence_rate.record(presence, now);
+            }
+            match event {
+                vrc::Event::OnJoinedRoom | vrc::Event::OnLeftRoom => {
+                    if *event == vrc::Event::OnLeftRoom {
+                        self.discard_teardown_leaves(line.time);
+                    }
+                    // send what was held back in the previous room first.
+                    self.release_held(true);
+                    self.flush_batches(true);
+                    if *event == vrc::Event::OnLeftRoom {
+                        self.send_session_summary(&line.time);
+                        // what was held back above has been sent with the name.
+                        self.world_name = None;
+                    }
+                    // a new session starts when entering a world.
+                    self.session = match event {
+                        vrc::Event::OnJoinedRoom => {
+                            self.send_world_entered(&line.time);
+                            Some(SessionStats::new(self.current_time_provider.current_time()))
+                        }
+                        _ => None,
+                    };
+                    // store the time that sending notification starts.
+                    self.notifiable_since = Some(
+                        self.current_time_provider.current_time()
+                            + self.settings.load_gate.period(),
+                    );
+                }
+                vrc::Event::OnPlayerJoined { .. } => {
+                    // the players already there are still being loaded, so wait for them to settle.
+                    if let LoadGate::Quiescence(period) = self.settings.load_gate {
+                        if self.is_loading() {
+                            self.notifiable_since =
+                                Some(self.current_time_provider.current_time() + period);
+                        }
+                    }
+                }
+                vrc::Event::ApplicationQuit => {
+                    // the next session starts from a new world, so stop gating.
+                    self.notifiable_since = None;
+                }
+                _ => (),
+            }
+
+            if self.is_notifiable() {
+                if let Some((presence, user_name)) = Presence::of(event) {
+                    if self.settings.notifies(presence) {
+                        let stay = stay.filter(|_| self.settings.show_time_in_instance);
+                        self.hold_presence(presence, user_name, line.time, stay);
+                    }
+                    return;
+                }
+            }
+        }
+
+        if let Some(message) = self.to_notification_object(line) {
+            self.send(&message);
+        }
+    }
+
+    fn flush(&mut self) {
+        self.release_held(false);
+        self.flush_batches(false);
+        self.send_rate_summary();
+        self.send_heartbeat();
+        self.check_disconnect();
+        if let Err(e) = self.client.flush() {
+            (self.error_handler)(&e);
+        }
+        if let Some(Err(e)) = self.presence_sink.as_ref().map(|s| s.flush()) {
+            (self.error_handler)(&e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use chrono::TimeZone;
+
+    use crate::testing::{MockTimeProvider, RecordingSink};
+
+    fn log_line(body: &str) -> LogLine {
+        LogLine::from_line(&format!("2021.12.01 23:23:13 Log        -  {}", body))
+            .expect("could not parse log line.")
+    }
+
+    fn notifier_with(
+        settings: NotifierSettings,
+    ) -> (
+        VrcToXsOverlayNotifier<MockTimeProvider, RecordingSink>,
+        MockTimeProvider,
+        RecordingSink,
+    ) {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        (notifier, clock, sink)
+    }
+
+    #[test]
+    fn summarize_user_names_lists_up_to_three_names() {
+        let names = |n: &[&str]| n.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!("Alice", summarize_user_names(&names(&["Alice"])));
+        assert_eq!(
+            "Alice and Bob",
+            summarize_user_names(&names(&["Alice", "Bob"]))
+        );
+        assert_eq!(
+            "Alice, Bob and Carol",
+            summarize_user_names(&names(&["Alice", "Bob", "Carol"]))
+        );
+        assert_eq!(
+            "Alice, Bob and 4 others",
+            summarize_user_names(&names(&["Alice", "Bob", "C", "D", "E", "F"]))
+        );
+    }
+
+    #[test]
+    fn notifier_batches_joins_within_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        for name in ["Alice", "Bob", "Carol", "Dave", "Eve", "Frank"] {
+            notifier.process_line(
+                log_line(&format!("[Behaviour] OnPlayerJoined {}", name)),
+                false,
+            );
+            clock.advance(Duration::milliseconds(400));
+        }
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(1));
+        notifier.flush();
+        assert_eq!(vec!["Alice, Bob and 4 others joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_keeps_single_join_in_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice joined."], sink.titles());
+        assert!(sink.messages.borrow()[0]
+            .to_json()
+            .unwrap()
+            .contains("\"useBase64Icon\":true"));
+    }
+
+    #[test]
+    fn notifier_separates_joins_outside_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(4));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice joined.", "Bob joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_batches_joins_and_leaves_separately() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Carol"), false);
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice and Carol joined.", "Bob left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_flushes_batch_on_room_change() {
+        let (mut notifier, _clock, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        assert_eq!(vec!["Alice and Bob joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_uses_audio_for_each_event() {
+        let settings = NotifierSettings {
+            join_audio: NotificationAudio::Custom("C:\\sounds\\join.ogg".to_owned()),
+            leave_audio: NotificationAudio::Warning,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let audio_path = |body: &str| {
+            let message = notifier.to_notification_object(log_line(body)).unwrap();
+            let json: serde_json::Value =
+                serde_json::from_str(&message.to_json().unwrap()).unwrap();
+            json["audioPath"].as_str().unwrap().to_owned()
+        };
+        assert_eq!(
+            "C:\\sounds\\join.ogg",
+            audio_path("[Behaviour] OnPlayerJoined Alice")
+        );
+        assert_eq!("warning", audio_path("[Behaviour] OnPlayerLeft Alice"));
+        assert_eq!(
+            "default",
+            audio_path("VRCApplication: OnApplicationQuit at 1.0")
+        );
+    }
+
+    #[test]
+    fn notifier_tracks_roster() {
+        let mut notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let roster = |notifier: &VrcToXsOverlayNotifier<_, _>| {
+            let mut names: Vec<String> = notifier
+                .current_roster()
+                .names()
+                .map(str::to_owned)
+                .collect();
+            names.sort();
+            names
+        };
+
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), true);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        assert_eq!(vec!["Alice"], roster(&notifier));
+
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        assert_eq!(vec!["Alice", "Bob"], roster(&notifier));
+
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Carol"), false);
+        assert_eq!(vec!["Alice", "Bob"], roster(&notifier));
+
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(vec!["Bob"], roster(&notifier));
+
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        assert!(roster(&notifier).is_empty());
+
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Dave"), false);
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        assert!(roster(&notifier).is_empty());
+    }
+
+    #[test]
+    fn notifier_shows_time_in_instance_on_leave() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            show_time_in_instance: true,
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::minutes(23) + Duration::seconds(10));
+        assert_eq!(
+            Some(Duration::minutes(23) + Duration::seconds(10)),
+            notifier
+                .current_roster()
+                .time_in_instance("Alice", clock.current_time())
+        );
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(
+            vec!["Alice joined.", "Alice left (was here 23 min)."],
+            sink.titles()
+        );
+    }
+
+    #[test]
+    fn notifier_omits_unknown_time_in_instance() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            show_time_in_instance: true,
+            ..Default::default()
+        });
+        // Alice was already there when reading started.
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        clock.advance(Duration::minutes(5));
+        assert_eq!(
+            None,
+            notifier
+                .current_roster()
+                .time_in_instance("Alice", clock.current_time())
+        );
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(vec!["Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_suppresses_flapping_leave_and_join() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            flap_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        clock.advance(Duration::seconds(1));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(10));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_suppresses_join_cancelled_by_leave() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            flap_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        clock.advance(Duration::seconds(2));
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        clock.advance(Duration::seconds(10));
+        notifier.flush();
+        assert_eq!(vec!["Bob joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_leave_and_join_outside_flap_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            flap_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        clock.advance(Duration::seconds(2));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(3));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice left."], sink.titles());
+
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice left.", "Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_coalesces_join_and_leave_into_pass_through() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            pass_through_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(1));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        clock.advance(Duration::seconds(10));
+        notifier.flush();
+        assert_eq!(vec!["Alice passed through."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_join_of_player_who_stayed() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            pass_through_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice joined."], sink.titles());
+
+        // the leave after the window is a separate one.
+        clock.advance(Duration::seconds(1));
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(vec!["Alice joined.", "Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_unmatched_leave_immediately() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            pass_through_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(vec!["Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_sends_only_joins_and_leaves_to_presence_sink() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let chatbox = RecordingSink::default();
+        let settings = NotifierSettings {
+            notify_authenticated: true,
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.set_presence_sink(chatbox.clone());
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            false,
+        );
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        notifier.process_line(log_line("VRCApplication: OnApplicationQuit at 1800"), false);
+        assert_eq!(
+            vec![
+                "Logged in as paralleltree.",
+                "Alice and Bob joined.",
+                "VRChat closed.",
+            ],
+            sink.titles()
+        );
+        assert_eq!(vec!["Alice and Bob joined."], chatbox.titles());
+    }
+
+    #[test]
+    fn anonymization_hides_names() {
+        assert_eq!("A***", Anonymization::Masked.apply("Alice"));
+        assert_eq!("あ***", Anonymization::Masked.apply(" あいう "));
+        assert_eq!("***", Anonymization::Masked.apply(""));
+        let hashed = Anonymization::Hashed.apply("Alice");
+        assert_eq!("Player dd8587", hashed);
+        assert_eq!(hashed, Anonymization::Hashed.apply("Alice "));
+        assert_ne!(hashed, Anonymization::Hashed.apply("Bob"));
+    }
+
+    #[test]
+    fn notifier_anonymizes_names_but_keeps_roster() {
+        for (anonymization, expected) in [
+            (
+                Anonymization::Masked,
+                vec![
+                    "A*** joined.",
+                    "B*** joined.",
+                    "A*** left.",
+                    "B*** was kicked.",
+                ],
+            ),
+            (
+                Anonymization::Hashed,
+                vec![
+                    "Player dd8587 joined.",
+                    "Player cba174 joined.",
+                    "Player dd8587 left.",
+                    "Player cba174 was kicked.",
+                ],
+            ),
+        ] {
+            let sink = RecordingSink::default();
+            let settings = NotifierSettings {
+                anonymize_names: Some(anonymization),
+                ..Default::default()
+            };
+            let mut notifier =
+                VrcToXsOverlayNotifier::new(sink.clone(), MockTimeProvider::new(), settings);
+            for body in [
+                "[Behaviour] OnPlayerJoined Alice",
+                "[Behaviour] OnPlayerJoined Bob",
+                "[Behaviour] OnPlayerLeft Alice",
+                "[ModerationManager] Bob has been kicked",
+            ] {
+                notifier.process_line(log_line(body), false);
+            }
+            assert_eq!(expected, sink.titles());
+            let roster = notifier.current_roster();
+            assert!(roster.contains("Bob"));
+            assert!(!roster.contains("Alice"));
+        }
+    }
+
+    fn toggled_titles(settings: NotifierSettings) -> Vec<String> {
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), MockTimeProvider::new(), settings);
+        for body in [
+            "[Behaviour] OnPlayerJoined Alice",
+            "[Behaviour] OnPlayerLeft Bob",
+            "[Behaviour] User Authenticated: paralleltree",
+            "[Behaviour] Instance is full",
+            "[ModerationManager] Bob has been kicked",
+            "VRCApplication: OnApplicationQuit at 1.0",
+        ] {
+            notifier.process_line(log_line(body), false);
+        }
+        notifier.flush();
+        sink.titles()
+    }
+
+    #[test]
+    fn notifier_defaults_to_joins_and_leaves_with_warnings() {
+        assert_eq!(
+            vec![
+                "Alice joined.",
+                "Bob left.",
+                "Bob was kicked.",
+                "VRChat closed."
+            ],
+            toggled_titles(NotifierSettings::default())
+        );
+    }
+
+    #[test]
+    fn notifier_notifies_only_enabled_events() {
+        let enabled = || NotifierSettings {
+            notify_authenticated: true,
+            notify_instance_full: true,
+            ..Default::default()
+        };
+        let all = vec![
+            "Alice joined.",
+            "Bob left.",
+            "Logged in as paralleltree.",
+            "The instance is full.",
+            "Bob was kicked.",
+            "VRChat closed.",
+        ];
+        assert_eq!(all, toggled_titles(enabled()));
+
+        type Disable = fn(&mut NotifierSettings);
+        let toggles: [(Disable, &str); 6] = [
+            (|s| s.notify_join = false, "Alice joined."),
+            (|s| s.notify_leave = false, "Bob left."),
+            (
+                |s| s.notify_authenticated = false,
+                "Logged in as paralleltree.",
+            ),
+            (|s| s.notify_instance_full = false, "The instance is full."),
+            (|s| s.notify_moderation = false, "Bob was kicked."),
+            (|s| s.notify_application_quit = false, "VRChat closed."),
+        ];
+        for (disable, title) in toggles {
+            let mut settings = enabled();
+            disable(&mut settings);
+            let expected: Vec<_> = all.iter().copied().filter(|t| *t != title).collect();
+            assert_eq!(expected, toggled_titles(settings), "{}", title);
+        }
+    }
+
+    #[test]
+    fn notifier_notifies_world_entered_if_enabled() {
+        for (notify_world, expected) in [(true, vec!["Entered The Great Pug."]), (false, vec![])] {
+            let sink = RecordingSink::default();
+            let settings = NotifierSettings {
+                notify_world,
+                ..Default::default()
+            };
+            let mut notifier =
+                VrcToXsOverlayNotifier::new(sink.clone(), MockTimeProvider::new(), settings);
+            notifier.process_line(log_line("[Behaviour] Entering Room: The Great Pug"), false);
+            notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+            notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+            assert_eq!(expected, sink.titles());
+        }
+    }
+
+    #[test]
+    fn notifier_skips_disabled_presence_while_holding() {
+        let sink = RecordingSink::default();
+        let clock = MockTimeProvider::new();
+        let settings = NotifierSettings {
+            notify_leave: false,
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Carol"), false);
+        clock.advance(Duration::seconds(3));
+        notifier.flush();
+        assert_eq!(vec!["Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_drops_leaves_of_teardown_before_leaving_room() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            teardown_window: Some(Duration::seconds(2)),
+            ..Default::default()
+        });
+        for name in ["Alice", "Bob", "Carol"] {
+            let line = format!("[Behaviour] OnPlayerLeft {}", name);
+            notifier.process_line(log_line_at("23:23:13", &line), false);
+        }
+        notifier.process_line(log_line_at("23:23:14", "[Behaviour] OnLeftRoom"), false);
+        clock.advance(Duration::seconds(10));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_keeps_leaves_before_teardown_window() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            teardown_window: Some(Duration::seconds(2)),
+            ..Default::default()
+        });
+        notifier.process_line(
+            log_line_at("23:23:10", "[Behaviour] OnPlayerLeft Alice"),
+            false,
+        );
+        notifier.process_line(
+            log_line_at("23:23:13", "[Behaviour] OnPlayerLeft Bob"),
+            false,
+        );
+        notifier.process_line(log_line_at("23:23:14", "[Behaviour] OnLeftRoom"), false);
+        assert_eq!(vec!["Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_leave_after_teardown_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            teardown_window: Some(Duration::seconds(2)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        clock.advance(Duration::seconds(1));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(1));
+        notifier.flush();
+        assert_eq!(vec!["Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_rejoin_within_teardown_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            teardown_window: Some(Duration::seconds(2)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice left.", "Alice joined."], sink.titles());
+
+        clock.advance(Duration::seconds(2));
+        notifier.flush();
+        assert_eq!(vec!["Alice left.", "Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn quiet_hours_contains_time_in_range() {
+        let quiet_hours = QuietHours {
+            start: NaiveTime::from_hms(2, 0, 0),
+            end: NaiveTime::from_hms(8, 0, 0),
+        };
+        assert!(quiet_hours.contains(NaiveTime::from_hms(2, 0, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(7, 59, 59)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(8, 0, 0)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(23, 0, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_wraps_past_midnight() {
+        let quiet_hours = QuietHours {
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(6, 0, 0),
+        };
+        assert!(quiet_hours.contains(NaiveTime::from_hms(23, 30, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(0, 0, 0)));
+        assert!(quiet_hours.contains(NaiveTime::from_hms(5, 59, 59)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(6, 0, 0)));
+        assert!(!quiet_hours.contains(NaiveTime::from_hms(12, 0, 0)));
+    }
+
+    #[test]
+    fn notifier_is_silent_during_quiet_hours() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms(22, 0, 0),
+                end: NaiveTime::from_hms(6, 0, 0),
+            }),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+
+        clock.set(
+            Local
+                .ymd(2021, 12, 1)
+                .and_hms(23, 30, 0)
+                .with_timezone(&Utc),
+        );
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert!(sink.titles().is_empty());
+
+        clock.set(Local.ymd(2021, 12, 2).and_hms(12, 0, 0).with_timezone(&Utc));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        assert_eq!(vec!["Bob joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_warns_instance_full_only_when_enabled() {
+        let line = "[Behaviour] Instance is full, cannot accept more players (32/32)";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_instance_full: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("The instance is full.", message.title());
+    }
+
+    #[test]
+    fn notifier_alerts_moderation_events() {
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let line = "[ModerationManager] paralleltree has been kicked from the instance.";
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("paralleltree was kicked.", message.title());
+
+        let line = "[ModerationManager] You have been warned by the instance owner.";
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("You were warned.", message.title());
+    }
+
+    #[test]
+    fn notifier_appends_timestamp_when_enabled() {
+        let settings = NotifierSettings {
+            show_timestamp: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let line = log_line("[Behaviour] OnPlayerJoined Alice");
+        let message = notifier.to_notification_object(line).unwrap();
+        assert_eq!("Alice joined.", message.title());
+        assert_eq!("(23:23)", message.content());
+
+        let line = log_line("[Behaviour] Instance is full, cannot accept more players");
+        let settings = NotifierSettings {
+            show_timestamp: true,
+            timestamp_format: "%H:%M:%S".to_owned(),
+            notify_instance_full: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(line).unwrap();
+        assert_eq!(
+            "Friends trying to join can't get in. (23:23:13)",
+            message.content()
+        );
+    }
+
+    #[test]
+    fn notifier_applies_message_defaults() {
+        let settings = NotifierSettings {
+            message_defaults: MessageDefaults {
+                timeout: 4.0,
+                volume: 0.2,
+                source_app: "vrc_doorkeeper-alt".to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier
+            .to_notification_object(log_line("[Behaviour] OnPlayerJoined Alice"))
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&message.to_json().unwrap()).unwrap();
+        assert_eq!("vrc_doorkeeper-alt", json["sourceApp"]);
+        assert_eq!(4.0, json["timeout"]);
+        assert_eq!(0.2, json["volume"]);
+
+        // the notifications with their own timeout keep it.
+        let message = notifier
+            .to_notification_object(log_line("[Behaviour] OnApplicationQuit"))
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&message.to_json().unwrap()).unwrap();
+        assert_eq!("vrc_doorkeeper-alt", json["sourceApp"]);
+        assert_eq!(1.0, json["timeout"]);
+    }
+
+    #[test]
+    fn notifier_uses_configured_warning_icon() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warning.png");
+        std::fs::write(&path, b"warning").unwrap();
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings {
+                icons: IconPaths {
+                    warning: Some(path),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        let message = notifier
+            .to_notification_object(log_line("[ModerationManager] Bob has been kicked"))
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&message.to_json().unwrap()).unwrap();
+        assert_eq!(base64::encode(b"warning"), json["icon"]);
+        assert_eq!(true, json["useBase64Icon"]);
+    }
+
+    #[test]
+    fn notifier_gives_warnings_longer_timeouts_than_joins() {
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let timeout = |line: &str| {
+            let message = notifier.to_notification_object(log_line(line)).unwrap();
+            let json: serde_json::Value =
+                serde_json::from_str(&message.to_json().unwrap()).unwrap();
+            json["timeout"].as_f64().unwrap()
+        };
+        let join = timeout("[Behaviour] OnPlayerJoined Alice");
+        let moderation = timeout("[ModerationManager] Bob has been kicked");
+        assert_eq!(1.0, join);
+        assert!(moderation > join);
+
+        // the categories are configurable.
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings {
+                timeouts: EventTimeouts {
+                    info: 2.0,
+                    warning: 8.0,
+                },
+                ..Default::default()
+            },
+        );
+        let message = notifier
+            .to_notification_object(log_line("[ModerationManager] Bob has been kicked"))
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&message.to_json().unwrap()).unwrap();
+        assert_eq!(8.0, json["timeout"]);
+    }
+
+    #[test]
+    fn notifier_summarizes_session_on_left_room() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            notify_session_summary: true,
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        for line in [
+            "[Behaviour] OnPlayerJoined Alice",
+            "[Behaviour] OnPlayerJoined Bob",
+            "[Behaviour] OnPlayerLeft Alice",
+            "[Behaviour] OnPlayerJoined Alice",
+            "[Behaviour] OnPlayerJoined Carol",
+        ] {
+            clock.advance(Duration::minutes(10));
+            notifier.process_line(log_line(line), false);
+        }
+        clock.advance(Duration::minutes(2));
+        assert_eq!(
+            Some(SessionSummary {
+                players_seen: 3,
+                joins: 4,
+                leaves: 1,
+                duration: Duration::minutes(52),
+            }),
+            notifier.session_summary()
+        );
+
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        assert_eq!(
+            Some(&"Session ended: 3 players seen over 52 min.".to_owned()),
+            sink.titles().last()
+        );
+        assert_eq!(
+            "4 joins, 1 leaves",
+            sink.messages.borrow().last().unwrap().content()
+        );
+        assert_eq!(None, notifier.session_summary());
+
+        // the counters start over in the next world.
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        clock.advance(Duration::minutes(1));
+        assert_eq!(
+            Some(SessionSummary {
+                players_seen: 0,
+                joins: 0,
+                leaves: 0,
+                duration: Duration::minutes(1),
+            }),
+            notifier.session_summary()
+        );
+    }
+
+    #[test]
+    fn notifier_does_not_summarize_session_by_default() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            batch_window: Some(Duration::seconds(3)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_counts_presence_rate_over_last_minute() {
+        let (mut notifier, clock, _) = notifier_with(NotifierSettings::default());
+        // 14:00:40, 14:00:50 and 14:01:00
+        clock.advance(Duration::seconds(40));
+        for user_name in ["Alice", "Bob", "Carol"] {
+            notifier.process_line(
+                log_line(&format!("[Behaviour] OnPlayerJoined {}", user_name)),
+                false,
+            );
+            clock.advance(Duration::seconds(10));
+        }
+        // 14:01:10
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        assert_eq!(
+            PresenceRate {
+                joins_per_minute: 3,
+                leaves_per_minute: 1,
+            },
+            notifier.presence_rate()
+        );
+
+        // the join at 14:00:40 expires at 14:01:40.
+        clock.advance(Duration::seconds(30));
+        assert_eq!(
+            PresenceRate {
+                joins_per_minute: 2,
+                leaves_per_minute: 1,
+            },
+            notifier.presence_rate()
+        );
+        clock.advance(Duration::seconds(40));
+        assert_eq!(
+            PresenceRate {
+                joins_per_minute: 0,
+                leaves_per_minute: 0,
+            },
+            notifier.presence_rate()
+        );
+    }
+
+    #[test]
+    fn notifier_does_not_count_presence_rate_of_first_lines() {
+        let (mut notifier, _, _) = notifier_with(NotifierSettings::default());
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        assert_eq!(0, notifier.presence_rate().joins_per_minute);
+    }
+
+    #[test]
+    fn notifier_reports_presence_rate_periodically() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            rate_summary_interval: Some(Duration::minutes(1)),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.flush();
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        clock.advance(Duration::seconds(30));
+        notifier.flush();
+        assert_eq!(2, sink.titles().len());
+
+        clock.advance(Duration::seconds(20));
+        notifier.flush();
+        assert_eq!(Some(&"2 joins/min".to_owned()), sink.titles().last());
+        assert_eq!(
+            "0 leaves/min",
+            sink.messages.borrow().last().unwrap().content()
+        );
+
+        // nothing is reported for a quiet minute.
+        clock.advance(Duration::minutes(1));
+        notifier.flush();
+        assert_eq!(3, sink.titles().len());
+    }
+
+    #[test]
+    fn notifier_sends_one_heartbeat_per_interval() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            heartbeat_interval: Some(Duration::minutes(10)),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), true);
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        // polled every second.
+        for _ in 0..(25 * 60) {
+            clock.advance(Duration::seconds(1));
+            notifier.flush();
+        }
+        assert_eq!(
+            vec![
+                "Still watching — 2 players in the instance.",
+                "Still watching — 2 players in the instance.",
+            ],
+            sink.titles()
+        );
+    }
+
+    #[test]
+    fn notifier_does_not_warn_disconnect_when_travelling() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            disconnect_window: Some(Duration::seconds(30)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        clock.advance(Duration::seconds(2));
+        notifier.flush();
+        notifier.process_line(log_line("[Behaviour] Entering Room: The Great Pug"), false);
+        // loading the world may take longer than the window.
+        clock.advance(Duration::minutes(2));
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_warns_disconnect_without_entering_world() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            disconnect_window: Some(Duration::seconds(30)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        clock.advance(Duration::seconds(29));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(1));
+        notifier.flush();
+        clock.advance(Duration::minutes(1));
+        notifier.process_line(log_line("[Behaviour] Entering Room: Home"), false);
+        notifier.flush();
+        assert_eq!(vec!["Disconnected from the instance."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_does_not_warn_disconnect_when_quitting() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            disconnect_window: Some(Duration::seconds(30)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        notifier.process_line(log_line("VRCApplication: OnApplicationQuit at 1.0"), false);
+        clock.advance(Duration::minutes(1));
+        notifier.flush();
+        assert_eq!(vec!["VRChat closed."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_sends_no_heartbeat_by_default() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), NotifierSettings::default());
+        notifier.flush();
+        clock.advance(Duration::hours(1));
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_prefixes_presence_with_world_name_when_enabled() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            show_world_name: true,
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        notifier.process_line(log_line("[Behaviour] Entering Room: The Great Pug"), false);
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnLeftRoom"), false);
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        assert_eq!(
+            vec![
+                "[The Great Pug] Alice joined.",
+                "[The Great Pug] Alice left.",
+                "Bob joined.",
+            ],
+            sink.titles()
+        );
+    }
+
+    #[test]
+    fn notifier_flags_player_count_differing_from_roster() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            reconcile_roster: true,
+            ..Default::default()
+        });
+        // reading started in the middle of the session, so Alice was missed.
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined paralleltree"), true);
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(log_line("[Behaviour] Player count: 3"), false);
+        assert_eq!(vec!["Bob joined.", "Player count mismatch."], sink.titles());
+        assert_eq!(
+            "3 in the instance, but 2 known.",
+            sink.messages.borrow().last().unwrap().content()
+        );
+    }
+
+    #[test]
+    fn notifier_accepts_player_count_matching_roster() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            reconcile_roster: true,
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] OnJoinedRoom"), true);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined paralleltree"), true);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] Player count: 2"), false);
+        assert!(sink.titles().is_empty());
+
+        // the count is not checked unless enabled.
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), NotifierSettings::default());
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] Player count: 5"), false);
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_drops_event_read_twice() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            dedup_window: Some(Duration::seconds(5)),
+            ..Default::default()
+        });
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        clock.advance(Duration::seconds(1));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+        assert_eq!(1, notifier.current_roster().len());
+
+        // the same line long after is not a duplicate of the one forgotten.
+        clock.advance(Duration::seconds(5));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined.", "Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_keeps_distinct_events_within_dedup_window() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            dedup_window: Some(Duration::seconds(5)),
+            ..Default::default()
+        });
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(
+            log_line_at("23:23:14", "[Behaviour] OnPlayerLeft Alice"),
+            false,
+        );
+        notifier.process_line(
+            log_line_at("23:23:15", "[Behaviour] OnPlayerJoined Alice"),
+            false,
+        );
+        assert_eq!(
+            vec![
+                "Alice joined.",
+                "Bob joined.",
+                "Alice left.",
+                "Alice joined."
+            ],
+            sink.titles()
+        );
+    }
+
+    #[test]
+    fn notifier_stacks_notifications_with_wrapping_indices() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            index_limit: Some(3),
+            ..Default::default()
+        };
+        let mut notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        clock.advance(Duration::seconds(10));
+        for user_name in ["Alice", "Bob", "Carol", "Dave"] {
+            notifier.process_line(
+                log_line(&format!("[Behaviour] OnPlayerJoined {}", user_name)),
+                false,
+            );
+        }
+        let indices: Vec<i32> = sink.messages.borrow().iter().map(|m| m.index()).collect();
+        assert_eq!(vec![0, 1, 2, 0], indices);
+    }
+
+    #[test]
+    fn notifier_sends_every_notification_with_index_zero_by_default() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), NotifierSettings::default());
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        let indices: Vec<i32> = sink.messages.borrow().iter().map(|m| m.index()).collect();
+        assert_eq!(vec![0, 0], indices);
+    }
+
+    #[test]
+    fn notifier_does_not_prefix_world_name_by_default() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), NotifierSettings::default());
+        notifier.process_line(log_line("[Behaviour] Entering Room: The Great Pug"), false);
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        clock.advance(Duration::seconds(10));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_confirms_login_when_enabled() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            notify_authenticated: true,
+            notify_authenticated_on_first_read: false,
+            ..Default::default()
+        });
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            false,
+        );
+        assert_eq!(vec!["Logged in as paralleltree."], sink.titles());
+
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings::default());
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            false,
+        );
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            true,
+        );
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_confirms_login_on_first_read_unless_suppressed() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            notify_authenticated: true,
+            notify_authenticated_on_first_read: true,
+            ..Default::default()
+        });
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            true,
+        );
+        // the other lines of the first read stay silent.
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        assert_eq!(vec!["Logged in as paralleltree."], sink.titles());
+
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            notify_authenticated: true,
+            notify_authenticated_on_first_read: false,
+            ..Default::default()
+        });
+        notifier.process_line(
+            log_line("[Behaviour] User Authenticated: paralleltree"),
+            true,
+        );
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_waits_for_roster_to_settle_on_slow_load() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            load_gate: LoadGate::Quiescence(Duration::milliseconds(1500)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        // the roster keeps loading for 12 seconds, longer than the fixed window.
+        for i in 0..12 {
+            clock.advance(Duration::seconds(1));
+            notifier.process_line(
+                log_line(&format!("[Behaviour] OnPlayerJoined Player{}", i)),
+                false,
+            );
+        }
+        clock.advance(Duration::seconds(1));
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Player0"), false);
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(2));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_delivers_joins_soon_after_quick_load() {
+        let (mut notifier, clock, sink) = notifier_with(NotifierSettings {
+            load_gate: LoadGate::Quiescence(Duration::milliseconds(1500)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        clock.advance(Duration::seconds(2));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+
+        // later joins do not extend the gate.
+        clock.advance(Duration::milliseconds(100));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Carol"), false);
+        assert_eq!(vec!["Alice joined.", "Carol joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_keeps_fixed_window_by_default() {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), NotifierSettings::default());
+        notifier.process_line(log_line("[Behaviour] Finished entering world."), false);
+        for _ in 0..4 {
+            clock.advance(Duration::seconds(1));
+            notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        }
+        clock.advance(Duration::seconds(1));
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec!["Alice joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_renders_custom_event_with_template() {
+        let settings = NotifierSettings {
+            custom_templates: HashMap::from([(
+                "dice".to_owned(),
+                "{user} rolled {number}.".to_owned(),
+            )]),
+            ..Default::default()
+        };
+        let sink = RecordingSink::default();
+        let mut notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), MockTimeProvider::new(), settings);
+        let rules = vec![vrc::log::CustomRule::new(
+            "dice".to_owned(),
+            r"^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)$",
+            "{user} rolled {number}.".to_owned(),
+        )
+        .unwrap()];
+        for body in ["[DiceMod] paralleltree rolled 6", "[OtherMod] hello"] {
+            let line = LogLine::from_line_with_rules(
+                &format!("2021.12.01 23:23:13 Log        -  {}", body),
+                vrc::log::AmbiguousTimePolicy::default(),
+                &rules,
+            )
+            .unwrap();
+            notifier.process_line(line, false);
+        }
+        assert_eq!(vec!["paralleltree rolled 6."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_renders_custom_event_with_replaced_rules() {
+        let sink = RecordingSink::default();
+        let mut notifier = VrcToXsOverlayNotifier::new(
+            sink.clone(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let dice = |template: &str| {
+            vrc::log::CustomRule::new(
+                "dice".to_owned(),
+                r"^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)$",
+                template.to_owned(),
+            )
+            .unwrap()
+        };
+        let rules = SharedCustomRules::new(vec![dice("{user} rolled {number}.")]);
+        notifier.set_custom_rules(rules.clone());
+        let line = || {
+            LogLine::from_line_with_rules(
+                "2021.12.01 23:23:13 Log        -  [DiceMod] paralleltree rolled 6",
+                vrc::log::AmbiguousTimePolicy::default(),
+                &rules.read(),
+            )
+            .unwrap()
+        };
+        notifier.process_line(line(), false);
+        rules.replace(vec![dice("Dice: {number}")]);
+        notifier.process_line(line(), false);
+        assert_eq!(vec!["paralleltree rolled 6.", "Dice: 6"], sink.titles());
+    }
+
+    struct FailingSink;
+
+    impl NotificationSink for FailingSink {
+        fn send_message(&self, _: &xsoverlay::MessageObject) -> Result<(), SendMessageError> {
+            Err(SendMessageError::PayloadTooLarge(70000))
+        }
+    }
+
+    #[test]
+    fn send_welcome_sends_configured_message() {
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            welcome_message: Some("Watching the log.".to_owned()),
+            ..Default::default()
+        };
+        assert!(send_welcome(&sink, &settings));
+        assert_eq!(vec!["VRC Doorkeeper"], sink.titles());
+        assert_eq!("Watching the log.", sink.messages.borrow()[0].content());
+    }
+
+    #[test]
+    fn send_welcome_inherits_message_defaults() {
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            message_defaults: MessageDefaults {
+                icon: xsoverlay::NotificationType::Custom("doorkeeper.png".to_owned()),
+                source_app: "vrc_doorkeeper-alt".to_owned(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        send_welcome(&sink, &settings);
+        let json: serde_json::Value =
+            serde_json::from_str(&sink.messages.borrow()[0].to_json().unwrap()).unwrap();
+        assert_eq!("doorkeeper.png", json["icon"]);
+        assert_eq!("vrc_doorkeeper-alt", json["sourceApp"]);
+    }
+
+    #[test]
+    fn send_welcome_sends_nothing_when_disabled() {
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            welcome_message: None,
+            ..Default::default()
+        };
+        assert!(!send_welcome(&sink, &settings));
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn send_welcome_survives_send_failure() {
+        assert!(!send_welcome(&FailingSink, &NotifierSettings::default()));
+    }
+
+    #[test]
+    fn notifier_reports_send_failure_to_error_handler() {
+        let mut notifier = VrcToXsOverlayNotifier::new(
+            FailingSink,
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let errors = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&errors);
+        notifier.set_error_handler(move |e| {
+            if let SendMessageError::PayloadTooLarge(size) = e {
+                recorded.borrow_mut().push(*size);
+            }
+        });
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        assert_eq!(vec![70000], *errors.borrow());
+    }
+
+    #[test]
+    fn notifier_is_silent_while_muted() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings::default());
+        let muted = notifier.muted_flag();
+        muted.store(true, Ordering::SeqCst);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        notifier.flush();
+        assert!(sink.titles().is_empty());
+
+        muted.store(false, Ordering::SeqCst);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.flush();
+        assert_eq!(vec!["Bob joined."], sink.titles());
+    }
+
+    #[test]
+    fn notifier_counts_events_and_notifications() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings::default());
+        let metrics = notifier.metrics();
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), true);
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Bob"), false);
+        notifier.process_line(log_line("[Behaviour] OnPlayerLeft Alice"), false);
+        notifier.flush();
+        let snapshot = metrics.snapshot();
+        let count = |name| snapshot.events.iter().find(|(n, _)| *n == name).unwrap().1;
+        assert_eq!(2, count("OnPlayerJoined"));
+        assert_eq!(1, count("OnPlayerLeft"));
+        assert_eq!(sink.titles().len() as u64, snapshot.notifications_sent);
+        assert_eq!(2, snapshot.notifications_sent);
+        assert_eq!(0, snapshot.send_failures);
+        assert_eq!(1, snapshot.roster_size);
+    }
+
+    #[test]
+    fn notifier_counts_send_failures() {
+        let mut notifier = VrcToXsOverlayNotifier::new(
+            FailingSink,
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        notifier.set_error_handler(|_| ());
+        notifier.process_line(log_line("[Behaviour] OnPlayerJoined Alice"), false);
+        let snapshot = notifier.metrics().snapshot();
+        assert_eq!(0, snapshot.notifications_sent);
+        assert_eq!(1, snapshot.send_failures);
+    }
+
+    fn log_line_at(time: &str, body: &str) -> LogLine {
+        LogLine::from_line(&format!("2021.12.01 {} Log        -  {}", time, body))
+            .expect("could not parse log line.")
+    }
+
+    const JOINING_A: &str =
+        "[Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(jp)";
+    const JOINING_B: &str =
+        "[Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:67890~region(jp)";
+
+    #[test]
+    fn notifier_detects_rejoin_to_same_instance() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            rejoin_window: Some(Duration::minutes(5)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line_at("23:00:00", JOINING_A), false);
+        notifier.process_line(
+            log_line_at("23:00:10", "[Behaviour] Finished entering world."),
+            false,
+        );
+        notifier.process_line(
+            log_line_at("23:20:00", "[Behaviour] OnPlayerJoined Alice"),
+            false,
+        );
+        // VRChat crashed and was launched again.
+        notifier.process_line(
+            log_line_at("23:21:00", "[Behaviour] User Authenticated: paralleltree"),
+            false,
+        );
+        notifier.process_line(log_line_at("23:24:00", JOINING_A), false);
+        assert_eq!(
+            Some(&"Rejoined the same instance.".to_owned()),
+            sink.titles().last()
+        );
+        assert_eq!("Japan", sink.messages.borrow().last().unwrap().content());
+    }
+
+    #[test]
+    fn notifier_does_not_report_rejoin_for_other_instance() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            rejoin_window: Some(Duration::minutes(5)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line_at("23:00:00", JOINING_A), false);
+        notifier.process_line(log_line_at("23:20:00", "[Behaviour] OnLeftRoom"), false);
+        notifier.process_line(log_line_at("23:20:05", JOINING_B), false);
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_does_not_report_rejoin_after_window() {
+        let (mut notifier, _, sink) = notifier_with(NotifierSettings {
+            rejoin_window: Some(Duration::minutes(5)),
+            ..Default::default()
+        });
+        notifier.process_line(log_line_at("23:00:00", JOINING_A), false);
+        notifier.process_line(log_line_at("23:20:00", "[Behaviour] OnLeftRoom"), false);
+        notifier.process_line(
+            log_line_at("23:30:00", "[Behaviour] Some unrelated message"),
+            false,
+        );
+        notifier.process_line(log_line_at("23:30:05", JOINING_A), false);
+        assert!(sink.titles().is_empty());
+    }
+
+    #[test]
+    fn notifier_notifies_friend_presence_only_when_enabled() {
+        let line = "[Behaviour] Friend paralleltree is now online";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_friend_presence: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("paralleltree is online.", message.title());
+    }
+
+    #[test]
+    fn notification_height_keeps_default_for_short_text() {
+        assert_eq!(175f32, notification_height("Alice joined.", ""));
+        assert_eq!(
+            175f32,
+            notification_height("The instance is full.", "Friends can't get in.")
+        );
+    }
+
+    #[test]
+    fn notification_height_grows_with_long_text() {
+        let long_name = "a".repeat(70);
+        // 3 lines of title.
+        assert_eq!(225f32, notification_height(&long_name, ""));
+        // 3 lines of title and 2 lines of content.
+        assert_eq!(
+            250f32,
+            notification_height(&long_name, "first line\nsecond line")
+        );
+    }
+
+    #[test]
+    fn notifier_sets_height_only_when_enabled() {
+        let line = format!("[Behaviour] OnPlayerJoined {}", "a".repeat(70));
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        let message = notifier.to_notification_object(log_line(&line)).unwrap();
+        assert_eq!(175f32, message.height());
+
+        let settings = NotifierSettings {
+            auto_height: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(&line)).unwrap();
+        assert_eq!(225f32, message.height());
+    }
+
+    #[test]
+    fn notifier_notifies_master_changed_only_when_enabled() {
+        let line = "[Behaviour] OnMasterClientSwitched paralleltree";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_master_changed: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("paralleltree is now the instance master.", message.title());
+    }
+
+    #[test]
+    fn notifier_warns_portal_dropped_only_when_enabled() {
+        let line = "[Behaviour] paralleltree dropped a portal to The Great Pug";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_portal_dropped: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("paralleltree dropped a portal.", message.title());
+        assert_eq!("The Great Pug", message.content());
+    }
+
+    #[test]
+    fn notifier_warns_download_stalled_only_when_enabled() {
+        let line = "[AssetBundleDownloadManager] [12] Download of World (The Great Pug) stalled";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_download_stalled: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("Download stalled.", message.title());
+        assert_eq!("World (The Great Pug)", message.content());
+        // stays longer than a join, as a failed load may follow.
+        assert!(message.timeout() > NotifierSettings::default().message_defaults.timeout);
+    }
+
+    #[test]
+    fn notifier_confirms_photo_taken_only_when_enabled() {
+        let line = "[VRC Camera] Took screenshot to: C:\\Pictures\\VRChat\\VRChat_2021-12-01.png";
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            NotifierSettings::default(),
+        );
+        assert!(notifier.to_notification_object(log_line(line)).is_none());
+
+        let settings = NotifierSettings {
+            notify_photo_taken: true,
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(
+            RecordingSink::default(),
+            MockTimeProvider::new(),
+            settings,
+        );
+        let message = notifier.to_notification_object(log_line(line)).unwrap();
+        assert_eq!("Screenshot saved.", message.title());
+        assert_eq!(
+            "C:\\Pictures\\VRChat\\VRChat_2021-12-01.png",
+            message.content()
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::cell::RefCell;
+use std::io;
+use std::net::TcpStream;
+
+use serde::{Deserialize, Serialize};
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use crate::xsoverlay::{MessageObject, NotificationSink, SendMessageError};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OvrToolkitCommand {
+    message_type: &'static str,
+    // OVR Toolkit expects the payload as a JSON string, not as an object.
+    json: String,
+}
+
+#[derive(Serialize, Debug)]
+struct OvrToolkitNotification<'a> {
+    title: &'a str,
+    body: &'a str,
+    timeout: f32,
+}
+
+// What OVR Toolkit answers, e.g. an acknowledgement.
+// Unknown fields are ignored, so that an addition to the API does not break sending.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct OvrToolkitResponse {
+    #[serde(default)]
+    message_type: Option<String>,
+}
+
+fn parse_response(text: &str) -> Result<OvrToolkitResponse, serde_json::Error> {
+    serde_json::from_str(text)
+}
+
+pub fn to_command_json(message: &MessageObject) -> Result<String, serde_json::Error> {
+    let notification = OvrToolkitNotification {
+        title: message.title(),
+        body: message.content(),
+        timeout: message.timeout(),
+    };
+    serde_json::to_string(&OvrToolkitCommand {
+        message_type: "SendNotification",
+        json: serde_json::to_string(&notification)?,
+    })
+}
+
+// Sends notifications to OVR Toolkit over its WebSocket API.
+// The connection is opened on the first message and reopened after a failure.
+pub struct OvrToolkitClient {
+    url: String,
+    socket: RefCell<Option<WebSocket<MaybeTlsStream<TcpStream>>>>,
+}
+
+impl OvrToolkitClient {
+    pub fn new() -> OvrToolkitClient {
+        Self::new_with_endpoint("127.0.0.1", 11450)
+    }
+
+    pub fn new_with_endpoint(host: &str, port: u16) -> OvrToolkitClient {
+        OvrToolkitClient {
+            url: format!("ws://{}:{}/api", host, port),
+            socket: RefCell::new(None),
+        }
+    }
+}
+
+impl Default for OvrToolkitClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationSink for OvrToolkitClient {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        let json = to_command_json(message)?;
+        let mut socket = self.socket.borrow_mut();
+        if socket.is_none() {
+            let (connected, _) = tungstenite::connect(self.url.as_str()).map_err(to_send_error)?;
+            *socket = Some(connected);
+        }
+        let result = socket.as_mut().unwrap().send(Message::text(json));
+        if let Err(e) = result {
+            // reconnect on the next message.
+            *socket = None;
+            return Err(to_send_error(e));
+        }
+        // the message has been sent whatever the responses are.
+        if !drain_responses(socket.as_mut().unwrap()) {
+            *socket = None;
+        }
+        Ok(())
+    }
+}
+
+// Reads the responses which have arrived without waiting for more, so that they do not pile up.
+// A response which can't be parsed is only reported.
+// Returns whether the connection is still open.
+fn drain_responses(socket: &mut WebSocket<MaybeTlsStream<TcpStream>>) -> bool {
+    let set_nonblocking =
+        |socket: &WebSocket<MaybeTlsStream<TcpStream>>, nonblocking| match socket.get_ref() {
+            MaybeTlsStream::Plain(stream) => stream.set_nonblocking(nonblocking).is_ok(),
+            _ => false,
+        };
+    if !set_nonblocking(socket, true) {
+        return true;
+    }
+    let is_open = loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                if let Err(e) = parse_response(&text) {
+                    eprintln!("Ignored an unexpected response from OVR Toolkit: {}", e);
+                }
+            }
+            Ok(Message::Close(_)) => break false,
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(e)) if e.kind() == io::ErrorKind::WouldBlock => break true,
+            Err(e) => {
+                eprintln!("{}", e);
+                break false;
+            }
+        }
+    };
+    set_nonblocking(socket, false);
+    is_open
+}
+
+fn to_send_error(e: tungstenite::Error) -> SendMessageError {
+    match e {
+        tungstenite::Error::Io(e) => SendMessageError::SendError(e),
+        e => SendMessageError::SendError(io::Error::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xsoverlay::MessageObjectBuilder;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn parse_response_ignores_unknown_fields() {
+        assert_eq!(
+            OvrToolkitResponse {
+                message_type: Some("Ack".to_owned()),
+            },
+            parse_response(r#"{"messageType":"Ack","extra":{"nested":[1,2]}}"#).unwrap()
+        );
+        assert_eq!(
+            OvrToolkitResponse { message_type: None },
+            parse_response("{}").unwrap()
+        );
+        assert!(parse_response("not json").is_err());
+    }
+
+    #[test]
+    fn send_message_succeeds_despite_unexpected_responses() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (responded, wait_responses) = mpsc::channel();
+        let server = thread::spawn(move || {
+            let mut socket = tungstenite::accept(listener.accept().unwrap().0).unwrap();
+            let mut commands = vec![socket.read().unwrap().into_text().unwrap().to_string()];
+            for response in ["not json", r#"{"messageType":42}"#, r#"{"unknown":true}"#] {
+                socket.send(Message::text(response)).unwrap();
+            }
+            socket.send(Message::binary(vec![0xff, 0x00])).unwrap();
+            responded.send(()).unwrap();
+            commands.push(socket.read().unwrap().into_text().unwrap().to_string());
+            commands
+        });
+
+        let client = OvrToolkitClient::new_with_endpoint("127.0.0.1", port);
+        let first = MessageObjectBuilder::new("Alice joined.".to_owned()).build();
+        assert!(client.send_message(&first).is_ok());
+        wait_responses.recv().unwrap();
+        // the responses have arrived by now, and are read while sending this one.
+        let second = MessageObjectBuilder::new("Bob joined.".to_owned()).build();
+        assert!(client.send_message(&second).is_ok());
+        let commands = server.join().unwrap();
+        assert_eq!(
+            vec![
+                to_command_json(&first).unwrap(),
+                to_command_json(&second).unwrap()
+            ],
+            commands
+        );
+    }
+
+    #[test]
+    fn command_json_wraps_notification_payload() {
+        let message = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_content("(23:41)".to_owned())
+            .set_timeout(1.5)
+            .build();
+        let actual: serde_json::Value =
+            serde_json::from_str(&to_command_json(&message).unwrap()).unwrap();
+        assert_eq!("SendNotification", actual["messageType"]);
+        let payload: serde_json::Value =
+            serde_json::from_str(actual["json"].as_str().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "title": "Alice joined.",
+                "body": "(23:41)",
+                "timeout": 1.5,
+            }),
+            payload
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::reader::{LogLineProcessor, VrChatLogProcessor};
+use crate::vrc::log::LogLine;
+
+// The async counterpart of LogLineProcessor.
+pub trait AsyncLogLineProcessor: Send + 'static {
+    fn process_line(&mut self, line: LogLine, is_first: bool) -> impl Future<Output = ()> + Send;
+
+    fn flush(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+enum Dispatch {
+    Line(LogLine, bool),
+    Flush,
+}
+
+// Hands the lines to the processors, each of which runs in its own task
+// so that a slow one, e.g. a webhook, does not hold back the others.
+#[derive(Default)]
+pub struct AsyncDispatcher {
+    senders: Vec<mpsc::UnboundedSender<Dispatch>>,
+}
+
+impl AsyncDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The task ends and returns the processor once the dispatcher is dropped.
+    pub fn spawn<P: AsyncLogLineProcessor>(&mut self, mut processor: P) -> JoinHandle<P> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        self.senders.push(sender);
+        tokio::spawn(async move {
+            while let Some(dispatch) = receiver.recv().await {
+                match dispatch {
+                    Dispatch::Line(line, is_first) => processor.process_line(line, is_first).await,
+                    Dispatch::Flush => processor.flush().await,
+                }
+            }
+            processor
+        })
+    }
+
+    fn dispatch<F>(&mut self, make: F)
+    where
+        F: Fn() -> Dispatch,
+    {
+        // a processor whose task has stopped is dropped.
+        self.senders.retain(|sender| sender.send(make()).is_ok());
+    }
+}
+
+impl LogLineProcessor for AsyncDispatcher {
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        self.dispatch(|| Dispatch::Line(line.clone(), is_first));
+    }
+
+    fn flush(&mut self) {
+        self.dispatch(|| Dispatch::Flush);
+    }
+}
+
+// Runs a synchronous processor, e.g. VrcToXsOverlayNotifier, on the blocking thread pool.
+pub struct Blocking<T> {
+    processor: Option<T>,
+}
+
+impl<T> Blocking<T> {
+    pub fn new(processor: T) -> Self {
+        Blocking {
+            processor: Some(processor),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.processor.unwrap()
+    }
+
+    async fn run<F>(&mut self, f: F)
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut T) + Send + 'static,
+    {
+        let mut processor = self.processor.take().unwrap();
+        let processor = tokio::task::spawn_blocking(move || {
+            f(&mut processor);
+            processor
+        })
+        .await
+        .expect("the processor panicked.");
+        self.processor = Some(processor);
+    }
+}
+
+impl<T: LogLineProcessor + Send + 'static> AsyncLogLineProcessor for Blocking<T> {
+    async fn process_line(&mut self, line: LogLine, is_first: bool) {
+        self.run(move |processor| processor.process_line(line, is_first))
+            .await;
+    }
+
+    async fn flush(&mut self) {
+        self.run(|processor| processor.flush()).await;
+    }
+}
+
+impl<T: LogLineProcessor + Send + 'static> VrChatLogProcessor<T> {
+    // Reads the log at poll_interval on the blocking thread pool until stop completes,
+    // and returns the processor then. It polls as the synchronous loop does
+    // rather than watching the files for changes.
+    // Errors are reported and retried on the next poll, as the synchronous loop does.
+    pub async fn run<F>(mut self, poll_interval: Duration, stop: F) -> Self
+    where
+        F: Future<Output = ()>,
+    {
+        let mut interval = tokio::time::interval(poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tokio::pin!(stop);
+        loop {
+            tokio::select! {
+                _ = &mut stop => return self,
+                _ = interval.tick() => (),
+            }
+            // the processor is moved to the blocking thread and back, as Blocking does.
+            let (processor, result) = tokio::task::spawn_blocking(move || {
+                let result = self.process_log();
+                (self, result)
+            })
+            .await
+            .expect("the log processor panicked.");
+            self = processor;
+            if let Err(e) = result {
+                eprintln!("{}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+
+    const FIXTURE: &str = "\
+2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree
+
+";
+
+    #[derive(Default)]
+    struct RecordingProcessor {
+        lines: Vec<(String, bool)>,
+        flushed: usize,
+        delay: Duration,
+    }
+
+    impl AsyncLogLineProcessor for RecordingProcessor {
+        async fn process_line(&mut self, line: LogLine, is_first: bool) {
+            tokio::time::sleep(self.delay).await;
+            self.lines.push((line.body, is_first));
+        }
+
+        async fn flush(&mut self) {
+            self.flushed += 1;
+        }
+    }
+
+    #[derive(Default)]
+    struct SyncRecordingProcessor {
+        lines: Vec<String>,
+    }
+
+    impl LogLineProcessor for SyncRecordingProcessor {
+        fn process_line(&mut self, line: LogLine, _is_first: bool) {
+            self.lines.push(line.body);
+        }
+    }
+
+    #[tokio::test]
+    async fn run_dispatches_lines_to_every_processor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(&path, FIXTURE).unwrap();
+
+        let mut dispatcher = AsyncDispatcher::new();
+        let recording = dispatcher.spawn(RecordingProcessor::default());
+        let blocking = dispatcher.spawn(Blocking::new(SyncRecordingProcessor::default()));
+        let processor = VrChatLogProcessor::new(dir.path().to_path_buf(), dispatcher);
+        let poll_interval = Duration::from_millis(10);
+        let stop = || tokio::time::sleep(Duration::from_millis(50));
+        let processor = processor.run(poll_interval, stop()).await;
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(
+                b"2021.12.01 23:26:39 Log        -  [Behaviour] OnPlayerLeft paralleltree\n\n",
+            )
+            .unwrap();
+        let processor = processor.run(poll_interval, stop()).await;
+        drop(processor);
+
+        let recording = recording.await.unwrap();
+        assert_eq!(
+            vec![
+                ("[Behaviour] Finished entering world.".to_owned(), true),
+                ("[Behaviour] OnPlayerJoined paralleltree".to_owned(), true),
+                ("[Behaviour] OnPlayerLeft paralleltree".to_owned(), false),
+            ],
+            recording.lines
+        );
+        assert!(recording.flushed > 0);
+        assert_eq!(
+            vec![
+                "[Behaviour] Finished entering world.",
+                "[Behaviour] OnPlayerJoined paralleltree",
+                "[Behaviour] OnPlayerLeft paralleltree",
+            ],
+            blocking.await.unwrap().into_inner().lines
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_processor_does_not_hold_back_others() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("output_log_23-23-00.txt"), FIXTURE).unwrap();
+
+        let mut dispatcher = AsyncDispatcher::new();
+        let slow = dispatcher.spawn(RecordingProcessor {
+            delay: Duration::from_secs(60),
+            ..Default::default()
+        });
+        let fast = dispatcher.spawn(RecordingProcessor::default());
+        let processor = VrChatLogProcessor::new(dir.path().to_path_buf(), dispatcher);
+        let processor = processor
+            .run(
+                Duration::from_millis(10),
+                tokio::time::sleep(Duration::from_millis(50)),
+            )
+            .await;
+        drop(processor);
+
+        assert_eq!(2, fast.await.unwrap().lines.len());
+        assert!(!slow.is_finished());
+        slow.abort();
+    }
+}
@@ -1,15 +1,60 @@
 use std::fs;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io;
-use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
-use crate::vrc::log::LogLine;
+use flate2::read::GzDecoder;
+
+use crate::vrc::log::{
+    parse_log_file_time, AmbiguousTimePolicy, LogLine, SharedCustomRules, LOG_FILE_NAME_PATTERN,
+};
+use crate::vrc::ParsedEvent;
+
+// A line appended to the log. Blank lines only separate the entries, so their text is not passed on.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadLine<'a> {
+    Text(&'a str),
+    Blank,
+}
+
+// Where the lines of the log come from, e.g. the latest log file or stdin.
+pub trait LineSource {
+    // Passes the lines which have become available since the last call.
+    fn read_lines(&mut self, callback: &mut dyn FnMut(ReadLine)) -> io::Result<()>;
+}
+
+// Reads the lines of a stream until its end, e.g. a log piped to stdin.
+pub struct BufReadSource<R: BufRead> {
+    reader: R,
+    read_bytes: u64,
+}
+
+impl<R: BufRead> BufReadSource<R> {
+    pub fn new(reader: R) -> Self {
+        BufReadSource {
+            reader,
+            read_bytes: 0,
+        }
+    }
+}
+
+impl<R: BufRead> LineSource for BufReadSource<R> {
+    fn read_lines(&mut self, callback: &mut dyn FnMut(ReadLine)) -> io::Result<()> {
+        read_lines_from(&mut self.reader, &mut self.read_bytes, None, callback)?;
+        Ok(())
+    }
+}
 
 #[derive(Debug)]
 struct ContinuousFileReader {
     file_path: PathBuf,
     read_bytes: u64,
+    // The most lines read at a time, and the rest in the next call. None reads to the end.
+    line_limit: Option<usize>,
+    // Whether the last call read to the end of the file.
+    reached_end: bool,
 }
 
 impl ContinuousFileReader {
@@ -17,63 +62,396 @@ impl ContinuousFileReader {
         ContinuousFileReader {
             file_path,
             read_bytes: 0,
+            line_limit: None,
+            reached_end: false,
+        }
+    }
+
+    fn with_line_limit(file_path: PathBuf, line_limit: Option<usize>) -> Self {
+        ContinuousFileReader {
+            line_limit,
+            ..Self::new(file_path)
         }
     }
 
     fn read_appended_lines<F>(&mut self, mut callback: F) -> io::Result<()>
     where
-        F: FnMut(&str),
+        F: FnMut(ReadLine),
+    {
+        let mut f = open_shared(&self.file_path)?;
+        let mut reader: Box<dyn BufRead> = if is_gzip(&self.file_path) {
+            // a compressed stream cannot seek, so skip the decompressed bytes already read.
+            let mut decoder = GzDecoder::new(f);
+            io::copy(&mut (&mut decoder).take(self.read_bytes), &mut io::sink())?;
+            Box::new(BufReader::new(decoder))
+        } else {
+            f.seek(SeekFrom::Start(self.read_bytes))?;
+            Box::new(BufReader::new(f))
+        };
+        self.reached_end = read_lines_from(
+            &mut reader,
+            &mut self.read_bytes,
+            self.line_limit,
+            &mut callback,
+        )?;
+        Ok(())
+    }
+}
+
+impl LineSource for ContinuousFileReader {
+    fn read_lines(&mut self, callback: &mut dyn FnMut(ReadLine)) -> io::Result<()> {
+        self.read_appended_lines(callback)
+    }
+}
+
+// Reads the lines to the end of the reader, or as many as the limit,
+// adding their length to read_bytes. Returns whether the end has been reached.
+fn read_lines_from(
+    reader: &mut dyn BufRead,
+    read_bytes: &mut u64,
+    line_limit: Option<usize>,
+    callback: &mut dyn FnMut(ReadLine),
+) -> io::Result<bool> {
+    let mut buf = String::new();
+    let mut lines = 0;
+    loop {
+        if line_limit.is_some_and(|limit| lines >= limit) {
+            return Ok(false);
+        }
+        let len = reader.read_line(&mut buf)?;
+        if len == 0 {
+            return Ok(true);
+        }
+        lines += 1;
+        let mut line = buf.trim_end();
+        if *read_bytes == 0 {
+            // the BOM, which some editors add when saving a copy of the log.
+            line = line.strip_prefix('\u{feff}').unwrap_or(line);
+        }
+        // the offset counts the raw bytes including the BOM and the line ending.
+        *read_bytes += len as u64;
+        if line.trim_start().is_empty() {
+            callback(ReadLine::Blank);
+        } else {
+            callback(ReadLine::Text(line));
+        }
+        buf.clear();
+    }
+}
+
+// Opens a log to read it from the start, decompressing it if it is gzipped.
+pub fn open_log(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    let f = open_shared(path)?;
+    Ok(if is_gzip(path) {
+        Box::new(BufReader::new(GzDecoder::new(f)))
+    } else {
+        Box::new(BufReader::new(f))
+    })
+}
+
+// Opens the log for reading without locking VRChat out of it.
+// On Windows, a file is opened exclusively unless the sharing is allowed explicitly,
+// which fails with a sharing violation while VRChat writes, rotates or deletes the log.
+fn open_shared(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        // FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+        options.share_mode(0x1 | 0x2 | 0x4);
+    }
+    options.open(path)
+}
+
+fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct LogEntry {
+    text: String,
+    is_first: bool,
+}
+
+// Joins the continuation lines, e.g. stack traces, to the entry they follow.
+// An entry is held back until it is known to be complete, which may be in the next poll.
+#[derive(Debug, Default)]
+struct LogEntryAssembler {
+    pending: Option<LogEntry>,
+}
+
+impl LogEntryAssembler {
+    // Returns the previous entry once the line shows that it is complete.
+    fn push(&mut self, line: &str, is_first: bool) -> Option<LogEntry> {
+        if LogLine::is_header(line) {
+            return self.pending.replace(LogEntry {
+                text: line.to_owned(),
+                is_first,
+            });
+        }
+        // a continuation without its header, e.g. at the start of reading, is dropped.
+        if let Some(pending) = &mut self.pending {
+            pending.text.push('\n');
+            pending.text.push_str(line);
+        }
+        None
+    }
+
+    fn take(&mut self) -> Option<LogEntry> {
+        self.pending.take()
+    }
+}
+
+// Traces what no pattern matched, to spot the wording changed by a VRChat update.
+#[derive(Debug, Default)]
+struct UnmatchedTracer {
+    header_mismatches: u64,
+}
+
+impl UnmatchedTracer {
+    // Counts the line if it does not start with the header, e.g. a line of a stack trace.
+    fn inspect_line(&mut self, line: &str) {
+        if !LogLine::is_header(line) {
+            self.header_mismatches += 1;
+        }
+    }
+
+    // The body to report, whose header matched but no event was found in it.
+    fn unmatched_body<'a>(&self, line: &'a LogLine) -> Option<&'a str> {
+        match line.event {
+            Some(_) => None,
+            None => Some(&line.body),
+        }
+    }
+}
+
+// Assembles the lines of a source into entries and passes them to the processor.
+#[derive(Default)]
+struct EntryReader {
+    assembler: LogEntryAssembler,
+    time_policy: AmbiguousTimePolicy,
+    custom_rules: SharedCustomRules,
+    // None unless tracing, so that nothing is done for it otherwise.
+    tracer: Option<UnmatchedTracer>,
+}
+
+impl EntryReader {
+    // Returns whether any line has been read.
+    fn read<S, T>(&mut self, source: &mut S, processor: &mut T, is_first: bool) -> io::Result<bool>
+    where
+        S: LineSource + ?Sized,
+        T: LogLineProcessor,
     {
-        let mut f = File::open(&self.file_path)?;
-        f.seek(SeekFrom::Start(self.read_bytes))?;
-        let mut reader = BufReader::new(f);
-        let mut buf = String::new();
-        loop {
-            let len = reader.read_line(&mut buf)?;
-            if len == 0 {
-                break;
+        let mut has_read = false;
+        source.read_lines(&mut |line| {
+            has_read = true;
+            let entry = match line {
+                ReadLine::Text(line) => {
+                    if let Some(tracer) = &mut self.tracer {
+                        tracer.inspect_line(line);
+                    }
+                    self.assembler.push(line, is_first)
+                }
+                // entries are separated by a blank line.
+                ReadLine::Blank => self.assembler.take(),
+            };
+            if let Some(entry) = entry {
+                self.process(processor, entry);
             }
-            self.read_bytes += len as u64;
-            callback(buf.trim_end());
-            buf.clear();
+        })?;
+        Ok(has_read)
+    }
+
+    // Passes the entry held back, which is known to be complete.
+    fn finish<T: LogLineProcessor>(&mut self, processor: &mut T) {
+        if let Some(entry) = self.assembler.take() {
+            self.process(processor, entry);
+        }
+    }
+
+    fn process<T: LogLineProcessor>(&self, processor: &mut T, entry: LogEntry) {
+        if let Some(log_line) =
+            LogLine::from_line_with_rules(&entry.text, self.time_policy, &self.custom_rules.read())
+        {
+            if let Some(body) = self
+                .tracer
+                .as_ref()
+                .and_then(|tracer| tracer.unmatched_body(&log_line))
+            {
+                eprintln!("Unmatched: {}", body);
+            }
+            processor.process_line(log_line, entry.is_first);
         }
-        Ok(())
     }
 }
 
 pub trait LogLineProcessor {
-    fn process_line(&mut self, line: LogLine, is_first: bool);
+    // Every line read, with or without an event.
+    // Only the lines with an event are passed on to process_event unless overridden.
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        if let Some(event) = line.into_parsed_event() {
+            self.process_event(event, is_first);
+        }
+    }
+
+    // For the processors which need only the events, not the other lines of the log.
+    fn process_event(&mut self, _event: ParsedEvent, _is_first: bool) {}
+
+    // Called after each poll to deliver anything held back by the processor.
+    fn flush(&mut self) {}
+}
+
+impl<T: LogLineProcessor> LogLineProcessor for &mut T {
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        (**self).process_line(line, is_first);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
 }
 
-pub struct VrChatLogProcessor<'a, T: LogLineProcessor> {
+// Passes every line to all of the processors.
+impl LogLineProcessor for Vec<Box<dyn LogLineProcessor>> {
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        for processor in self.iter_mut() {
+            processor.process_line(line.clone(), is_first);
+        }
+    }
+
+    fn flush(&mut self) {
+        for processor in self.iter_mut() {
+            processor.flush();
+        }
+    }
+}
+
+pub struct VrChatLogProcessor<T: LogLineProcessor> {
     log_dir: PathBuf,
-    processor: &'a mut T,
+    processor: T,
     reader: Option<ContinuousFileReader>,
+    entries: EntryReader,
+    prefer_file_name_time: bool,
+    // Set while the log directory does not exist, e.g. VRChat has never been launched.
+    waiting_for_log_dir: bool,
+    line_limit: Option<usize>,
+    // Set until the end of the log is reached after starting to read it,
+    // as the lines before it were written before reading started.
+    catching_up: bool,
 }
 
-impl<T: LogLineProcessor> VrChatLogProcessor<'_, T> {
-    pub fn new(log_dir: PathBuf, processor: &mut T) -> VrChatLogProcessor<T> {
+impl<T: LogLineProcessor> VrChatLogProcessor<T> {
+    pub fn new(log_dir: PathBuf, processor: T) -> VrChatLogProcessor<T> {
         VrChatLogProcessor {
             log_dir,
             processor,
             reader: None,
+            entries: EntryReader::default(),
+            prefer_file_name_time: false,
+            waiting_for_log_dir: false,
+            line_limit: None,
+            catching_up: false,
+        }
+    }
+
+    // The log directory does not exist, while process_log keeps succeeding.
+    pub fn is_waiting_for_log_dir(&self) -> bool {
+        self.waiting_for_log_dir
+    }
+
+    pub fn processor_mut(&mut self) -> &mut T {
+        &mut self.processor
+    }
+
+    // Orders the logs by the time in their names rather than the modified time,
+    // which is not kept when the logs are copied.
+    pub fn set_prefer_file_name_time(&mut self, prefer_file_name_time: bool) {
+        self.prefer_file_name_time = prefer_file_name_time;
+    }
+
+    pub fn set_time_policy(&mut self, time_policy: AmbiguousTimePolicy) {
+        self.entries.time_policy = time_policy;
+    }
+
+    // Pass SharedCustomRules to replace the rules while running.
+    pub fn set_custom_rules(&mut self, custom_rules: impl Into<SharedCustomRules>) {
+        self.entries.custom_rules = custom_rules.into();
+    }
+
+    // Prints the bodies of the entries in which no event was found,
+    // and counts the lines without the header.
+    pub fn set_trace_unmatched(&mut self, trace_unmatched: bool) {
+        self.entries.tracer = trace_unmatched.then(UnmatchedTracer::default);
+    }
+
+    // The number of the lines without the header so far, if tracing.
+    pub fn header_mismatches(&self) -> Option<u64> {
+        self.entries
+            .tracer
+            .as_ref()
+            .map(|tracer| tracer.header_mismatches)
+    }
+
+    // Reads at most this many lines in each call of process_log and the rest in the next ones,
+    // so that a burst of lines is notified over several polls. None reads to the end.
+    pub fn set_line_limit(&mut self, line_limit: Option<usize>) {
+        self.line_limit = line_limit;
+        if let Some(reader) = &mut self.reader {
+            reader.line_limit = line_limit;
         }
     }
 
     pub fn process_log(&mut self) -> io::Result<()> {
-        let log_entries = crate::vrc::log::get_log_entries(&self.log_dir)?;
-        let latest_log_path = match find_latest_log_path(log_entries) {
-            Some(path) => path,
+        let scan = || {
+            crate::vrc::log::get_log_entries(&self.log_dir)
+                .map(|entries| modified_times(entries.map(|entry| entry.path()), modified_time))
+        };
+        let (log_entries, latest) = match scan_latest_log(scan, self.prefer_file_name_time) {
+            Ok(scanned) => scanned,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                // report only once and keep waiting quietly.
+                if !self.waiting_for_log_dir {
+                    eprintln!(
+                        "Waiting for the log directory to be created: {}.",
+                        self.log_dir.to_string_lossy()
+                    );
+                    self.waiting_for_log_dir = true;
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        self.waiting_for_log_dir = false;
+        let (latest_log_path, latest_modified_at) = match latest {
+            Some(latest) => latest,
             None => return Err(io::Error::from(io::ErrorKind::NotFound)),
         };
-        let mut is_first = false;
         if let Some(current_monitor) = &self.reader {
-            if current_monitor.file_path != latest_log_path {
+            let current = log_entries
+                .iter()
+                .find(|(path, _)| *path == current_monitor.file_path);
+            // the current log may look older for a moment while the other one is written.
+            let should_switch = match current {
+                Some((current_path, current_modified_at)) => is_newer_log(
+                    (current_path, *current_modified_at),
+                    (&latest_log_path, latest_modified_at),
+                ),
+                None => true,
+            };
+            if current_monitor.file_path != latest_log_path && should_switch {
                 eprintln!(
                     "Changing reading log file: {}.",
                     latest_log_path.to_str().unwrap()
                 );
-                self.reader = Some(ContinuousFileReader::new(latest_log_path));
+                // the entry of the previous file can't continue in the new one.
+                self.entries.finish(&mut self.processor);
+                self.reader = Some(ContinuousFileReader::with_line_limit(
+                    latest_log_path,
+                    self.line_limit,
+                ));
+                // what was written before switching is not live, as on a fresh start.
+                self.catching_up = true;
             }
         } else {
             // ログなしから新規作成されたものを読み出すとき
@@ -81,33 +459,875 @@ impl<T: LogLineProcessor> VrChatLogProcessor<'_, T> {
                 "Starting reading log file: {}.",
                 latest_log_path.to_str().unwrap()
             );
-            self.reader = Some(ContinuousFileReader::new(latest_log_path));
-            is_first = true;
+            self.reader = Some(ContinuousFileReader::with_line_limit(
+                latest_log_path,
+                self.line_limit,
+            ));
+            self.catching_up = true;
         }
         if let Some(monitor) = &mut self.reader {
-            return monitor.read_appended_lines(|line| {
-                if let Some(log_line) = LogLine::from_line(line) {
-                    self.processor.process_line(log_line, is_first);
-                }
-            });
+            let has_read = self
+                .entries
+                .read(monitor, &mut self.processor, self.catching_up)?;
+            if monitor.reached_end {
+                self.catching_up = false;
+            }
+            // nothing has been appended since the last poll, so the entry is complete.
+            if !has_read {
+                self.entries.finish(&mut self.processor);
+            }
+        }
+        self.processor.flush();
+        Ok(())
+    }
+}
+
+// Processes the lines of any source as they are read, e.g. a log piped to stdin.
+// Every line is live, since the source does not tell which ones were written before reading started.
+pub struct LineSourceProcessor<S: LineSource, T: LogLineProcessor> {
+    source: S,
+    processor: T,
+    entries: EntryReader,
+}
+
+impl<S: LineSource, T: LogLineProcessor> LineSourceProcessor<S, T> {
+    pub fn new(source: S, processor: T) -> LineSourceProcessor<S, T> {
+        LineSourceProcessor {
+            source,
+            processor,
+            entries: EntryReader::default(),
         }
+    }
+
+    pub fn processor_mut(&mut self) -> &mut T {
+        &mut self.processor
+    }
+
+    pub fn set_time_policy(&mut self, time_policy: AmbiguousTimePolicy) {
+        self.entries.time_policy = time_policy;
+    }
+
+    // Pass SharedCustomRules to replace the rules while running.
+    pub fn set_custom_rules(&mut self, custom_rules: impl Into<SharedCustomRules>) {
+        self.entries.custom_rules = custom_rules.into();
+    }
+
+    // Prints the bodies of the entries in which no event was found,
+    // and counts the lines without the header.
+    pub fn set_trace_unmatched(&mut self, trace_unmatched: bool) {
+        self.entries.tracer = trace_unmatched.then(UnmatchedTracer::default);
+    }
+
+    // The number of the lines without the header so far, if tracing.
+    pub fn header_mismatches(&self) -> Option<u64> {
+        self.entries
+            .tracer
+            .as_ref()
+            .map(|tracer| tracer.header_mismatches)
+    }
+
+    // Reads the lines available from the source.
+    // The last entry is regarded as complete, as the source is read to its end.
+    pub fn process_lines(&mut self) -> io::Result<()> {
+        self.entries
+            .read(&mut self.source, &mut self.processor, false)?;
+        self.entries.finish(&mut self.processor);
+        self.processor.flush();
         Ok(())
     }
 }
 
-fn find_latest_log_path<I>(log_entries: I) -> Option<PathBuf>
+// The modified time of the log itself rather than of a symlink to it.
+fn modified_time(path: &Path) -> io::Result<SystemTime> {
+    fs::metadata(path)?.modified()
+}
+
+// A log whose modified time can't be read, e.g. deleted by rotation, is left out.
+fn modified_times<I, F>(log_paths: I, modified_time: F) -> Vec<(PathBuf, SystemTime)>
+where
+    I: Iterator<Item = PathBuf>,
+    F: Fn(&Path) -> io::Result<SystemTime>,
+{
+    log_paths
+        .filter_map(|path| {
+            let modified_at = modified_time(&path).ok()?;
+            Some((path, modified_at))
+        })
+        .collect()
+}
+
+type ScannedLogs = (Vec<(PathBuf, SystemTime)>, Option<(PathBuf, SystemTime)>);
+
+// Scans the logs and selects the latest one, scanning once more if none was found,
+// as the only log may have been left out while it was rotated.
+fn scan_latest_log<F>(mut scan: F, prefer_file_name_time: bool) -> io::Result<ScannedLogs>
+where
+    F: FnMut() -> io::Result<Vec<(PathBuf, SystemTime)>>,
+{
+    let select = |log_entries: &[(PathBuf, SystemTime)]| {
+        select_latest_log_path(log_entries.iter().cloned(), prefer_file_name_time)
+            .and_then(|path| log_entries.iter().find(|(p, _)| *p == path).cloned())
+    };
+    let log_entries = scan()?;
+    if let Some(latest) = select(&log_entries) {
+        return Ok((log_entries, Some(latest)));
+    }
+    let log_entries = scan()?;
+    let latest = select(&log_entries);
+    Ok((log_entries, latest))
+}
+
+// How much newer another log must be to switch to it.
+const LOG_SWITCH_THRESHOLD: Duration = Duration::from_secs(2);
+
+// A log is newer if the time in its name is later, or if it was modified
+// more than LOG_SWITCH_THRESHOLD later than the current one.
+fn is_newer_log(current: (&Path, SystemTime), candidate: (&Path, SystemTime)) -> bool {
+    let name_time = |path: &Path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_log_file_time)
+    };
+    if let (Some(current_time), Some(candidate_time)) =
+        (name_time(current.0), name_time(candidate.0))
+    {
+        if candidate_time > current_time {
+            return true;
+        }
+    }
+    candidate
+        .1
+        .duration_since(current.1)
+        .is_ok_and(|newer_by| newer_by > LOG_SWITCH_THRESHOLD)
+}
+
+// Picks the most recently modified log file.
+// Ties are broken by the file name, which embeds the time the log was created,
+// so that the same file is chosen on every poll.
+// If prefer_file_name_time is set, the time in the name is compared first
+// and the logs without it are regarded as older.
+fn select_latest_log_path<I>(entries: I, prefer_file_name_time: bool) -> Option<PathBuf>
 where
-    I: Iterator<Item = fs::DirEntry>,
+    I: Iterator<Item = (PathBuf, SystemTime)>,
 {
-    let entries = log_entries.filter_map(|entry| {
-        let metadata = entry.metadata().ok()?;
-        let modified_at = metadata.modified().ok()?;
-        Some((entry, modified_at))
+    let entries = entries.filter_map(|(path, modified_at)| {
+        let name = path.file_name()?.to_str()?.to_owned();
+        if !LOG_FILE_NAME_PATTERN.is_match(&name) {
+            return None;
+        }
+        let name_time = if prefer_file_name_time {
+            parse_log_file_time(&name)
+        } else {
+            None
+        };
+        Some((path, name, name_time, modified_at))
     });
-    let latest_modified_entry =
-        entries.max_by(|(_, modified_a), (_, modified_b)| modified_a.cmp(modified_b));
-    if let Some((entry, _)) = latest_modified_entry {
-        return Some(entry.path());
+    let latest_modified_entry = entries.max_by(
+        |(_, name_a, name_time_a, modified_a), (_, name_b, name_time_b, modified_b)| {
+            name_time_a
+                .cmp(name_time_b)
+                .then_with(|| modified_a.cmp(modified_b))
+                .then_with(|| name_a.cmp(name_b))
+        },
+    );
+    latest_modified_entry.map(|(path, _, _, _)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::Write;
+    use std::time::Duration;
+
+    use crate::notifier::{DefaultCurrentTimeProvider, NotifierSettings, VrcToXsOverlayNotifier};
+    use crate::testing::{RecordingProcessor, RecordingSink};
+    use crate::vrc::log::CustomRule;
+
+    #[test]
+    fn process_log_waits_quietly_for_missing_log_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_dir = dir.path().join("VRChat");
+        let mut processor = VrChatLogProcessor::new(log_dir.clone(), RecordingProcessor::default());
+        for _ in 0..3 {
+            assert!(processor.process_log().is_ok());
+            assert!(processor.waiting_for_log_dir);
+        }
+
+        fs::create_dir(&log_dir).unwrap();
+        fs::write(
+            log_dir.join("output_log_23-23-00.txt"),
+            "2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.\n\n",
+        )
+        .unwrap();
+        assert!(processor.process_log().is_ok());
+        assert!(!processor.waiting_for_log_dir);
+        assert_eq!(
+            vec![("[Behaviour] Finished entering world.".to_owned(), true)],
+            processor.processor_mut().lines
+        );
+    }
+
+    #[test]
+    fn process_log_treats_lines_of_switched_file_as_first() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("output_log_2021-12-01_23-00-00.txt"),
+            "2021.12.01 23:00:00 Log        -  [Behaviour] Finished entering world.\n\n",
+        )
+        .unwrap();
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_path_buf(), RecordingProcessor::default());
+        processor.process_log().unwrap();
+
+        let path = dir.path().join("output_log_2021-12-01_23-30-00.txt");
+        fs::write(
+            &path,
+            "2021.12.01 23:30:00 Log        -  [Behaviour] OnPlayerJoined paralleltree\n\n",
+        )
+        .unwrap();
+        processor.process_log().unwrap();
+        let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(b"2021.12.01 23:30:05 Log        -  [Behaviour] OnPlayerLeft paralleltree\n\n")
+            .unwrap();
+        processor.process_log().unwrap();
+        assert_eq!(
+            vec![
+                ("[Behaviour] Finished entering world.".to_owned(), true),
+                ("[Behaviour] OnPlayerJoined paralleltree".to_owned(), true),
+                ("[Behaviour] OnPlayerLeft paralleltree".to_owned(), false),
+            ],
+            processor.processor_mut().lines
+        );
+    }
+
+    #[test]
+    fn process_log_joins_continuation_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(
+            &path,
+            "\
+2021.12.01 23:23:12 Error      -  NullReferenceException: Object reference not set
+  at VRC.Core.ApiWorld.Fetch () [0x00000] in <00000000000000000000000000000000>:0
+
+2021.12.01 23:23:13 Exception  -  InvalidOperationException: Sequence contains no elements
+",
+        )
+        .unwrap();
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_path_buf(), RecordingProcessor::default());
+        processor.process_log().unwrap();
+        assert_eq!(
+            vec![(
+                "NullReferenceException: Object reference not set\n  at VRC.Core.ApiWorld.Fetch () [0x00000] in <00000000000000000000000000000000>:0".to_owned(),
+                true
+            )],
+            processor.processor_mut().lines
+        );
+
+        // the entry continues across polls.
+        let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(b"  at System.Linq.Enumerable.First[TSource] () [0x00010]\n")
+            .unwrap();
+        processor.process_log().unwrap();
+        assert_eq!(1, processor.processor_mut().lines.len());
+
+        // an empty poll completes the entry.
+        processor.process_log().unwrap();
+        assert_eq!(
+            (
+                "InvalidOperationException: Sequence contains no elements\n  at System.Linq.Enumerable.First[TSource] () [0x00010]".to_owned(),
+                true
+            ),
+            processor.processor_mut().lines[1]
+        );
+    }
+
+    #[test]
+    fn log_entry_assembler_drops_orphan_continuation_lines() {
+        let mut assembler = LogEntryAssembler::default();
+        assert_eq!(
+            None,
+            assembler.push("  at UnityEngine.Object.Instantiate ()", true)
+        );
+        assert_eq!(None, assembler.take());
+    }
+
+    fn entry(name: &str, modified_at: SystemTime) -> (PathBuf, SystemTime) {
+        (PathBuf::from("logs").join(name), modified_at)
+    }
+
+    #[test]
+    fn scan_latest_log_scans_again_when_metadata_of_only_log_fails() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let path = PathBuf::from("logs").join("output_log_2021-12-01_23-23-12.txt");
+        let scans = RefCell::new(0);
+        let scan = || {
+            *scans.borrow_mut() += 1;
+            let is_first_scan = *scans.borrow() == 1;
+            // the log is being rotated during the first scan.
+            Ok(modified_times(std::iter::once(path.clone()), |_| {
+                if is_first_scan {
+                    Err(io::Error::from(io::ErrorKind::NotFound))
+                } else {
+                    Ok(modified_at)
+                }
+            }))
+        };
+        let (_, latest) = scan_latest_log(scan, false).unwrap();
+        assert_eq!(Some((path, modified_at)), latest);
+        assert_eq!(2, *scans.borrow());
+    }
+
+    #[test]
+    fn scan_latest_log_gives_up_after_second_scan_without_logs() {
+        let scans = RefCell::new(0);
+        let scan = || {
+            *scans.borrow_mut() += 1;
+            Ok(Vec::new())
+        };
+        let (log_entries, latest) = scan_latest_log(scan, false).unwrap();
+        assert!(log_entries.is_empty());
+        assert_eq!(None, latest);
+        assert_eq!(2, *scans.borrow());
+    }
+
+    #[test]
+    fn continuous_file_reader_reads_gzip_log() {
+        let original = "\
+2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree
+";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(original.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let mut reader = ContinuousFileReader::new(path);
+        let mut lines = Vec::new();
+        reader
+            .read_appended_lines(|line| lines.push(owned(line)))
+            .unwrap();
+        assert_eq!(
+            vec![
+                Some(
+                    "2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world."
+                        .to_owned()
+                ),
+                None,
+                Some(
+                    "2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree"
+                        .to_owned()
+                ),
+            ],
+            lines
+        );
+        assert_eq!(original.len() as u64, reader.read_bytes);
+
+        reader
+            .read_appended_lines(|line| lines.push(owned(line)))
+            .unwrap();
+        assert_eq!(3, lines.len());
+    }
+
+    // None for a blank line.
+    fn owned(line: ReadLine) -> Option<String> {
+        match line {
+            ReadLine::Text(text) => Some(text.to_owned()),
+            ReadLine::Blank => None,
+        }
+    }
+
+    #[test]
+    fn continuous_file_reader_reads_up_to_line_limit_at_a_time() {
+        let lines: Vec<String> = (0..5)
+            .map(|i| {
+                format!(
+                    "2021.12.01 23:23:1{} Log        -  [Behaviour] OnPlayerJoined P{}",
+                    i, i
+                )
+            })
+            .collect();
+        let original: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(&path, &original).unwrap();
+
+        let mut reader = ContinuousFileReader::with_line_limit(path, Some(2));
+        let mut read = Vec::new();
+        let mut offsets = Vec::new();
+        for _ in 0..3 {
+            reader
+                .read_appended_lines(|line| read.push(owned(line).unwrap()))
+                .unwrap();
+            offsets.push((reader.read_bytes, reader.reached_end));
+        }
+        assert_eq!(lines, read);
+        let offset = |count: usize| {
+            (lines[..count]
+                .iter()
+                .map(|line| line.len() + 1)
+                .sum::<usize>()) as u64
+        };
+        assert_eq!(
+            vec![(offset(2), false), (offset(4), false), (offset(5), true)],
+            offsets
+        );
+    }
+
+    #[test]
+    fn process_log_spreads_burst_over_polls() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(
+            &path,
+            "2021.12.01 23:23:12 Log        -  [Behaviour] OnPlayerJoined Alice\n",
+        )
+        .unwrap();
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_owned(), RecordingProcessor::default());
+        processor.set_line_limit(Some(2));
+        processor.process_log().unwrap();
+
+        let burst: String = (0..5)
+            .map(|i| {
+                format!(
+                    "2021.12.01 23:23:2{} Log        -  [Behaviour] OnPlayerJoined P{}\n\n",
+                    i, i
+                )
+            })
+            .collect();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(burst.as_bytes())
+            .unwrap();
+        let mut counts = Vec::new();
+        for _ in 0..5 {
+            processor.process_log().unwrap();
+            counts.push(processor.processor_mut().lines.len());
+        }
+        // two lines in each poll, an entry and a blank line, until the burst is consumed.
+        assert_eq!(vec![2, 3, 4, 5, 6], counts);
+        assert!(processor.processor_mut().lines[1..]
+            .iter()
+            .all(|(_, is_first)| !is_first));
+    }
+
+    #[test]
+    fn process_log_keeps_lines_before_reaching_end_as_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        let log: String = (0..3)
+            .map(|i| {
+                format!(
+                    "2021.12.01 23:23:1{} Log        -  [Behaviour] OnPlayerJoined P{}\n\n",
+                    i, i
+                )
+            })
+            .collect();
+        fs::write(&path, log).unwrap();
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_owned(), RecordingProcessor::default());
+        processor.set_line_limit(Some(2));
+        for _ in 0..4 {
+            processor.process_log().unwrap();
+        }
+        let lines = &processor.processor_mut().lines;
+        assert_eq!(3, lines.len());
+        assert!(lines.iter().all(|(_, is_first)| *is_first));
+    }
+
+    #[test]
+    fn continuous_file_reader_strips_bom_and_blank_lines() {
+        let original = "\u{feff}2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.\r\n\r\n  \t\r\n2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree\r\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(&path, original).unwrap();
+
+        let mut reader = ContinuousFileReader::new(path.clone());
+        let mut lines = Vec::new();
+        reader
+            .read_appended_lines(|line| lines.push(owned(line)))
+            .unwrap();
+        assert_eq!(
+            vec![
+                Some(
+                    "2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world."
+                        .to_owned()
+                ),
+                None,
+                None,
+                Some(
+                    "2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree"
+                        .to_owned()
+                ),
+            ],
+            lines
+        );
+        assert_eq!(original.len() as u64, reader.read_bytes);
+
+        // a BOM-like character after the start of the file is kept.
+        let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all("\u{feff}\r\n".as_bytes()).unwrap();
+        lines.clear();
+        reader
+            .read_appended_lines(|line| lines.push(owned(line)))
+            .unwrap();
+        assert_eq!(vec![Some("\u{feff}".to_owned())], lines);
+        assert_eq!(original.len() as u64 + 5, reader.read_bytes);
+    }
+
+    #[test]
+    fn process_log_notifies_through_notifier_in_one_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("output_log_23-23-00.txt"),
+            "2021.12.01 23:23:00 Log        -  [Behaviour] User Authenticated: paralleltree\n\n\
+             2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.\n\n\
+             2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined Alice\n\n",
+        )
+        .unwrap();
+        let sink = RecordingSink::default();
+        let settings = NotifierSettings {
+            notify_authenticated: true,
+            ..Default::default()
+        };
+        let notifier =
+            VrcToXsOverlayNotifier::new(sink.clone(), DefaultCurrentTimeProvider {}, settings);
+        let mut processor = VrChatLogProcessor::new(dir.path().to_path_buf(), notifier);
+        processor.process_log().unwrap();
+        // the lines written before reading started are not live, except for the login.
+        assert_eq!(vec!["Logged in as paralleltree."], sink.titles());
+    }
+
+    #[test]
+    fn continuous_file_reader_reads_while_log_is_open_for_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        // held open as VRChat does while it is running.
+        let mut writer = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .unwrap();
+        writer
+            .write_all(b"2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.\n")
+            .unwrap();
+        writer.flush().unwrap();
+
+        let mut reader = ContinuousFileReader::new(path);
+        let mut lines = Vec::new();
+        reader
+            .read_appended_lines(|line| lines.push(owned(line)))
+            .unwrap();
+        assert_eq!(
+            vec![Some(
+                "2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.".to_owned()
+            )],
+            lines
+        );
+    }
+
+    #[test]
+    fn line_source_processor_notifies_every_line_of_stream() {
+        let input = "\
+2021.12.01 23:23:12 Log        -  [Behaviour] Some unrelated message
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined Alice
+
+2021.12.01 23:23:14 Log        -  [Behaviour] OnPlayerLeft Alice
+";
+        let sink = RecordingSink::default();
+        let notifier = VrcToXsOverlayNotifier::new(
+            sink.clone(),
+            DefaultCurrentTimeProvider {},
+            NotifierSettings::default(),
+        );
+        let mut processor =
+            LineSourceProcessor::new(BufReadSource::new(input.as_bytes()), notifier);
+        processor.process_lines().unwrap();
+        // the last entry is passed without waiting for the next poll.
+        assert_eq!(vec!["Alice joined.", "Alice left."], sink.titles());
+    }
+
+    #[test]
+    fn unmatched_tracer_reports_only_body_without_event() {
+        let tracer = UnmatchedTracer::default();
+        let unmatched = LogLine::from_line(
+            "2021.12.01 23:23:12 Log        -  [Behaviour] OnPlayerArrived Alice",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("[Behaviour] OnPlayerArrived Alice"),
+            tracer.unmatched_body(&unmatched)
+        );
+        let matched = LogLine::from_line(
+            "2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined Alice",
+        )
+        .unwrap();
+        assert_eq!(None, tracer.unmatched_body(&matched));
+    }
+
+    #[test]
+    fn line_source_processor_counts_lines_without_header_when_tracing() {
+        let input = "\
+2021.12.01 23:23:12 Exception  -  NullReferenceException
+  at VRC.Core.Foo ()
+  at VRC.Core.Bar ()
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined Alice
+";
+        let mut processor = LineSourceProcessor::new(
+            BufReadSource::new(input.as_bytes()),
+            RecordingProcessor::default(),
+        );
+        assert_eq!(None, processor.header_mismatches());
+        processor.set_trace_unmatched(true);
+        processor.process_lines().unwrap();
+        assert_eq!(Some(2), processor.header_mismatches());
+        // the entries are passed as without tracing.
+        assert_eq!(2, processor.processor_mut().lines.len());
+    }
+
+    #[derive(Default)]
+    struct CustomEventProcessor {
+        names: Vec<String>,
+    }
+
+    impl LogLineProcessor for CustomEventProcessor {
+        fn process_event(&mut self, event: ParsedEvent, _: bool) {
+            if let crate::vrc::Event::Custom { name, .. } = event.event {
+                self.names.push(name);
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct EventProcessor {
+        events: Vec<(ParsedEvent, bool)>,
+    }
+
+    impl LogLineProcessor for EventProcessor {
+        fn process_event(&mut self, event: ParsedEvent, is_first: bool) {
+            self.events.push((event, is_first));
+        }
+    }
+
+    #[test]
+    fn process_line_passes_only_lines_with_event_to_process_event() {
+        let line = |body: &str| {
+            LogLine::from_line(&format!("2021.12.01 23:23:13 Log        -  {}", body)).unwrap()
+        };
+        let joined = line("[Behaviour] OnPlayerJoined paralleltree");
+        let mut processor = EventProcessor::default();
+        processor.process_line(joined.clone(), true);
+        processor.process_line(line("[Behaviour] Some unknown text"), false);
+        assert_eq!(
+            vec![(joined.into_parsed_event().unwrap(), true)],
+            processor.events
+        );
+    }
+
+    #[test]
+    fn process_log_matches_lines_against_replaced_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        let line = "2021.12.01 23:23:13 Log        -  [DiceMod] paralleltree rolled 6\n\n";
+        fs::write(&path, line).unwrap();
+        let rule = |name: &str, pattern: &str| {
+            CustomRule::new(name.to_owned(), pattern, String::new()).unwrap()
+        };
+        let rules = SharedCustomRules::new(vec![rule("dice", r"^\[DiceMod\] ")]);
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_path_buf(), CustomEventProcessor::default());
+        processor.set_custom_rules(rules.clone());
+        processor.process_log().unwrap();
+
+        rules.replace(vec![rule("roll", r"rolled \d+$")]);
+        let mut f = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(line.as_bytes()).unwrap();
+        processor.process_log().unwrap();
+
+        rules.replace(Vec::new());
+        f.write_all(line.as_bytes()).unwrap();
+        processor.process_log().unwrap();
+        assert_eq!(vec!["dice", "roll"], processor.processor_mut().names);
+    }
+
+    #[test]
+    fn process_log_reads_bom_prefixed_log() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("output_log_23-23-00.txt"),
+            "\u{feff}2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.\n\n\n2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree\n\n",
+        )
+        .unwrap();
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_path_buf(), RecordingProcessor::default());
+        processor.process_log().unwrap();
+        assert_eq!(
+            vec![
+                ("[Behaviour] Finished entering world.".to_owned(), true),
+                ("[Behaviour] OnPlayerJoined paralleltree".to_owned(), true),
+            ],
+            processor.processor_mut().lines
+        );
+    }
+
+    #[test]
+    fn is_newer_log_requires_margin_of_modified_time() {
+        let now = SystemTime::now();
+        let current = Path::new("output_log_23-23-00.txt");
+        let candidate = Path::new("output_log_23-24-00.txt");
+        assert!(!is_newer_log(
+            (current, now),
+            (candidate, now + Duration::from_secs(1))
+        ));
+        assert!(!is_newer_log(
+            (current, now),
+            (candidate, now - Duration::from_secs(5))
+        ));
+        assert!(is_newer_log(
+            (current, now),
+            (candidate, now + Duration::from_secs(3))
+        ));
+    }
+
+    #[test]
+    fn is_newer_log_accepts_later_file_name_time() {
+        let now = SystemTime::now();
+        let current = Path::new("output_log_2021-12-01_23-00-00.txt");
+        assert!(is_newer_log(
+            (current, now),
+            (Path::new("output_log_2021-12-01_23-30-00.txt"), now)
+        ));
+        assert!(!is_newer_log(
+            (current, now),
+            (Path::new("output_log_2021-12-01_22-30-00.txt"), now)
+        ));
+    }
+
+    #[test]
+    fn process_log_does_not_switch_on_alternating_modified_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("output_log_23-23-00.txt");
+        let second = dir.path().join("output_log_23-24-00.txt");
+        fs::write(&first, "").unwrap();
+        fs::write(&second, "").unwrap();
+        let base = SystemTime::now();
+        let set_modified = |path: &Path, time: SystemTime| {
+            File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(time)
+                .unwrap();
+        };
+        set_modified(&first, base + Duration::from_secs(1));
+        set_modified(&second, base);
+        let mut processor =
+            VrChatLogProcessor::new(dir.path().to_path_buf(), RecordingProcessor::default());
+        processor.process_log().unwrap();
+        assert_eq!(first, processor.reader.as_ref().unwrap().file_path);
+
+        // the files overtake each other by a moment while both are written.
+        for i in 1..5 {
+            let (newer, older) = if i % 2 == 1 {
+                (&second, &first)
+            } else {
+                (&first, &second)
+            };
+            set_modified(older, base + Duration::from_secs(i));
+            set_modified(newer, base + Duration::from_secs(i + 1));
+            processor.process_log().unwrap();
+            assert_eq!(first, processor.reader.as_ref().unwrap().file_path);
+        }
+
+        set_modified(&second, base + Duration::from_secs(10));
+        processor.process_log().unwrap();
+        assert_eq!(second, processor.reader.as_ref().unwrap().file_path);
+    }
+
+    #[test]
+    fn select_latest_log_path_accepts_gzip_log() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let entries = vec![entry("output_log_2021-12-01_23-23-12.txt.gz", modified_at)];
+        assert_eq!(
+            Some(PathBuf::from("logs").join("output_log_2021-12-01_23-23-12.txt.gz")),
+            select_latest_log_path(entries.into_iter(), false)
+        );
+    }
+
+    #[test]
+    fn select_latest_log_path_breaks_ties_by_name() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let newer = "output_log_2021-12-01_23-23-12.txt";
+        let older = "output_log_2021-12-01_21-05-40.txt";
+        for entries in [
+            vec![entry(newer, modified_at), entry(older, modified_at)],
+            vec![entry(older, modified_at), entry(newer, modified_at)],
+        ] {
+            assert_eq!(
+                Some(PathBuf::from("logs").join(newer)),
+                select_latest_log_path(entries.into_iter(), false)
+            );
+        }
+    }
+
+    #[test]
+    fn select_latest_log_path_prefers_modified_time() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let entries = vec![
+            entry("output_log_2021-12-01_23-23-12.txt", modified_at),
+            entry(
+                "output_log_2021-12-01_21-05-40.txt",
+                modified_at + Duration::from_secs(1),
+            ),
+        ];
+        assert_eq!(
+            Some(PathBuf::from("logs").join("output_log_2021-12-01_21-05-40.txt")),
+            select_latest_log_path(entries.into_iter(), false)
+        );
+    }
+
+    #[test]
+    fn select_latest_log_path_ignores_other_files() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let entries = vec![
+            entry("output_log_2021-12-01_23-23-12.txt", modified_at),
+            entry("Player.log", modified_at + Duration::from_secs(1)),
+        ];
+        assert_eq!(
+            Some(PathBuf::from("logs").join("output_log_2021-12-01_23-23-12.txt")),
+            select_latest_log_path(entries.into_iter(), false)
+        );
+    }
+
+    #[test]
+    fn select_latest_log_path_prefers_file_name_time_when_requested() {
+        let modified_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_638_368_592);
+        let entries = vec![
+            entry("output_log_2021-12-01_23-23-12.txt", modified_at),
+            entry(
+                "output_log_2021-12-01_21-05-40.txt",
+                modified_at + Duration::from_secs(1),
+            ),
+            entry(
+                "output_log_23-59-59.txt",
+                modified_at + Duration::from_secs(2),
+            ),
+        ];
+        assert_eq!(
+            Some(PathBuf::from("logs").join("output_log_2021-12-01_23-23-12.txt")),
+            select_latest_log_path(entries.into_iter(), true)
+        );
     }
-    None
 }
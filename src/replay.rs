@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use crate::reader::LogLineProcessor;
+use crate::vrc::log::LogLine;
+
+// How often the processor is flushed while waiting, so that the batches and the held events
+// are released on time rather than with the next line.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+// Passes the lines of a recorded log to the processor as far apart as they were logged,
+// divided by the speed, e.g. to demonstrate the load gate and batching without VRChat.
+// The wait is std::thread::sleep when replaying, or advances a clock in tests.
+pub struct ReplayPacer<T: LogLineProcessor, W: FnMut(Duration)> {
+    processor: T,
+    speed: f64,
+    wait: W,
+    last_time: Option<DateTime<Local>>,
+}
+
+impl<T: LogLineProcessor, W: FnMut(Duration)> ReplayPacer<T, W> {
+    // The speed must be positive, e.g. 2.0 replays twice as fast as logged.
+    pub fn new(processor: T, speed: f64, wait: W) -> Self {
+        ReplayPacer {
+            processor,
+            speed,
+            wait,
+            last_time: None,
+        }
+    }
+
+    pub fn processor_mut(&mut self) -> &mut T {
+        &mut self.processor
+    }
+
+    // Keeps flushing for a while after the last line, not scaled by the speed,
+    // so that what is held back at the end of the log is still sent.
+    pub fn linger(&mut self, duration: Duration) {
+        self.wait_for(duration);
+    }
+
+    // A line logged before the previous one, e.g. around a DST transition, is passed at once.
+    fn delay_until(&self, time: DateTime<Local>) -> Duration {
+        let elapsed = match self.last_time {
+            Some(last_time) => (time - last_time).to_std().unwrap_or_default(),
+            None => Duration::ZERO,
+        };
+        Duration::from_secs_f64(elapsed.as_secs_f64() / self.speed)
+    }
+
+    fn wait_for(&mut self, delay: Duration) {
+        let mut remaining = delay;
+        while !remaining.is_zero() {
+            let step = remaining.min(FLUSH_INTERVAL);
+            (self.wait)(step);
+            remaining -= step;
+            self.processor.flush();
+        }
+    }
+}
+
+impl<T: LogLineProcessor, W: FnMut(Duration)> LogLineProcessor for ReplayPacer<T, W> {
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        let delay = self.delay_until(line.time);
+        self.wait_for(delay);
+        self.last_time = Some(self.last_time.map_or(line.time, |last| last.max(line.time)));
+        self.processor.process_line(line, is_first);
+    }
+
+    fn flush(&mut self) {
+        self.processor.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Duration as ChronoDuration;
+
+    use crate::notifier::{CurrentTimeProvider, NotifierSettings, VrcToXsOverlayNotifier};
+    use crate::testing::{MockTimeProvider, RecordingProcessor, RecordingSink};
+
+    // Advances the clock instead of sleeping.
+    fn advance(clock: &MockTimeProvider) -> impl FnMut(Duration) {
+        let clock = clock.clone();
+        move |step| clock.advance(ChronoDuration::from_std(step).unwrap())
+    }
+
+    fn log_line(time: &str, body: &str) -> LogLine {
+        LogLine::from_line(&format!("2021.12.01 {} Log        -  {}", time, body))
+            .expect("could not parse log line.")
+    }
+
+    #[test]
+    fn replay_pacer_spaces_lines_by_scaled_log_time() {
+        let clock = MockTimeProvider::new();
+        let start = clock.current_time();
+        let processor = RecordingProcessor::with_clock(clock.clone());
+        let mut pacer = ReplayPacer::new(processor, 2.0, advance(&clock));
+        for (time, body) in [
+            ("23:23:10", "first"),
+            ("23:23:14", "second"),
+            ("23:23:14", "third"),
+            // logged earlier than the previous one, so passed at once.
+            ("23:23:12", "fourth"),
+            ("23:23:20", "fifth"),
+        ] {
+            pacer.process_line(log_line(time, body), false);
+        }
+        let at = |seconds| start + ChronoDuration::seconds(seconds);
+        let processor = pacer.processor_mut();
+        let passed: Vec<_> = processor
+            .lines
+            .iter()
+            .map(|(body, _)| body.as_str())
+            .zip(processor.passed_at.iter().copied())
+            .collect();
+        assert_eq!(
+            vec![
+                ("first", at(0)),
+                ("second", at(2)),
+                ("third", at(2)),
+                ("fourth", at(2)),
+                ("fifth", at(5)),
+            ],
+            passed
+        );
+        // flushed at every step of the wait.
+        assert_eq!(20, pacer.processor_mut().flushes);
+    }
+
+    #[test]
+    fn replay_pacer_makes_load_gate_and_batching_observable() {
+        let clock = MockTimeProvider::new();
+        let start = clock.current_time();
+        let sink = RecordingSink::with_clock(clock.clone());
+        let settings = NotifierSettings {
+            batch_window: Some(ChronoDuration::seconds(3)),
+            ..Default::default()
+        };
+        let notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        let mut pacer = ReplayPacer::new(notifier, 1.0, advance(&clock));
+        for (time, body) in [
+            ("23:23:00", "[Behaviour] Finished entering world."),
+            // still within the 5 seconds after entering the world.
+            ("23:23:02", "[Behaviour] OnPlayerJoined Alice"),
+            ("23:23:10", "[Behaviour] OnPlayerJoined Bob"),
+            ("23:23:11", "[Behaviour] OnPlayerJoined Carol"),
+            ("23:23:20", "[Behaviour] OnPlayerLeft Bob"),
+        ] {
+            pacer.process_line(log_line(time, body), false);
+        }
+        pacer.linger(Duration::from_secs(5));
+        let at = |seconds| start + ChronoDuration::seconds(seconds);
+        let sent: Vec<_> = sink
+            .titles()
+            .into_iter()
+            .zip(sink.sent_at.borrow().iter().copied())
+            .collect();
+        assert_eq!(
+            vec![
+                ("Bob and Carol joined.".to_owned(), at(13)),
+                ("Bob left.".to_owned(), at(23)),
+            ],
+            sent
+        );
+    }
+}
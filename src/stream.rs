@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::reader::{LogLineProcessor, VrChatLogProcessor};
+use crate::vrc::ParsedEvent;
+
+struct EventCollector {
+    events: VecDeque<ParsedEvent>,
+    include_existing: bool,
+}
+
+impl LogLineProcessor for EventCollector {
+    fn process_event(&mut self, event: ParsedEvent, is_first: bool) {
+        if is_first && !self.include_existing {
+            return;
+        }
+        self.events.push_back(event);
+    }
+}
+
+// Monitors the VRChat log directory and yields the events appended to the latest log.
+//
+// Iterating blocks until the next event is found, polling the log at poll_interval.
+// Errors while reading the log are retried silently; use poll() to observe them.
+pub struct EventStream {
+    processor: VrChatLogProcessor<EventCollector>,
+    poll_interval: Duration,
+}
+
+impl EventStream {
+    // Events already written to the log when the stream starts are skipped.
+    pub fn new(log_dir: PathBuf) -> EventStream {
+        Self::new_with_existing(log_dir, false)
+    }
+
+    // If include_existing is set, the events already written to the log are yielded first.
+    pub fn new_with_existing(log_dir: PathBuf, include_existing: bool) -> EventStream {
+        let collector = EventCollector {
+            events: VecDeque::new(),
+            include_existing,
+        };
+        EventStream {
+            processor: VrChatLogProcessor::new(log_dir, collector),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn set_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // Reads the log once and returns the events found since the last call.
+    pub fn poll(&mut self) -> io::Result<Vec<ParsedEvent>> {
+        self.processor.process_log()?;
+        Ok(self.processor.processor_mut().events.drain(..).collect())
+    }
+}
+
+impl Iterator for EventStream {
+    type Item = ParsedEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.processor.processor_mut().events.pop_front() {
+                return Some(event);
+            }
+            if self.processor.process_log().is_ok()
+                && !self.processor.processor_mut().events.is_empty()
+            {
+                continue;
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, OpenOptions};
+    use std::io::Write;
+
+    use crate::vrc::Event;
+
+    const FIXTURE: &str = "\
+2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree (usr_a58186d2-54f9-44c8-902b-6e03927f66c1)
+
+2021.12.01 23:23:14 Log        -  [Behaviour] Some unrelated message
+";
+
+    fn write_fixture(dir: &tempfile::TempDir) -> PathBuf {
+        let path = dir.path().join("output_log_23-23-00.txt");
+        File::create(&path)
+            .unwrap()
+            .write_all(FIXTURE.as_bytes())
+            .unwrap();
+        path
+    }
+
+    fn append(path: &PathBuf, text: &str) {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    fn events(events: Vec<ParsedEvent>) -> Vec<Event> {
+        events.into_iter().map(|parsed| parsed.event).collect()
+    }
+
+    #[test]
+    fn event_stream_yields_existing_events_when_requested() {
+        let dir = tempfile::tempdir().unwrap();
+        write_fixture(&dir);
+        let mut stream = EventStream::new_with_existing(dir.path().to_path_buf(), true);
+        assert_eq!(
+            vec![
+                Event::OnJoinedRoom,
+                Event::OnPlayerJoined {
+                    user_name: "paralleltree".to_owned()
+                },
+            ],
+            events(stream.poll().unwrap())
+        );
+        assert!(stream.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn event_stream_yields_only_appended_events_by_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_fixture(&dir);
+        let mut stream = EventStream::new(dir.path().to_path_buf());
+        assert!(stream.poll().unwrap().is_empty());
+
+        append(
+            &path,
+            "2021.12.01 23:26:39 Log        -  [Behaviour] OnPlayerLeft paralleltree\n",
+        );
+        let parsed = stream.next().unwrap();
+        assert_eq!(
+            Event::OnPlayerLeft {
+                user_name: "paralleltree".to_owned()
+            },
+            parsed.event
+        );
+        assert_eq!(
+            "2021-12-01 23:26:39",
+            parsed.time.format("%F %T").to_string()
+        );
+        assert_eq!("[Behaviour] OnPlayerLeft paralleltree", parsed.raw);
+    }
+
+    #[test]
+    fn event_stream_reports_missing_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut stream = EventStream::new(dir.path().to_path_buf());
+        assert_eq!(io::ErrorKind::NotFound, stream.poll().unwrap_err().kind());
+    }
+}
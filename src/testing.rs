@@ -0,0 +1,112 @@
+// Fixtures shared by the tests, also included by the integration tests.
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+use crate::notifier::CurrentTimeProvider;
+use crate::reader::LogLineProcessor;
+use crate::vrc::log::LogLine;
+use crate::xsoverlay::{MessageObject, NotificationSink, SendMessageError};
+
+// A clock which only moves when told to, shared between its clones.
+#[derive(Clone)]
+pub struct MockTimeProvider {
+    now: Rc<Cell<DateTime<Utc>>>,
+}
+
+impl MockTimeProvider {
+    pub fn new() -> Self {
+        MockTimeProvider {
+            now: Rc::new(Cell::new(Utc.ymd(2021, 12, 1).and_hms(14, 0, 0))),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.now.set(now);
+    }
+}
+
+impl Default for MockTimeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CurrentTimeProvider for MockTimeProvider {
+    fn current_time(&self) -> DateTime<Utc> {
+        self.now.get()
+    }
+}
+
+// Records the messages sent, shared between its clones,
+// and the time of the clock they were sent at if it has one.
+#[derive(Clone, Default)]
+pub struct RecordingSink {
+    pub messages: Rc<RefCell<Vec<MessageObject>>>,
+    pub sent_at: Rc<RefCell<Vec<DateTime<Utc>>>>,
+    clock: Option<MockTimeProvider>,
+}
+
+impl RecordingSink {
+    pub fn with_clock(clock: MockTimeProvider) -> Self {
+        RecordingSink {
+            clock: Some(clock),
+            ..Default::default()
+        }
+    }
+
+    pub fn titles(&self) -> Vec<String> {
+        self.messages
+            .borrow()
+            .iter()
+            .map(|m| m.title().to_owned())
+            .collect()
+    }
+}
+
+impl NotificationSink for RecordingSink {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        self.messages.borrow_mut().push(message.clone());
+        if let Some(clock) = &self.clock {
+            self.sent_at.borrow_mut().push(clock.current_time());
+        }
+        Ok(())
+    }
+}
+
+// Records the body of each line and whether it was read on the first read,
+// and the time of the clock it was passed at if it has one.
+#[derive(Default)]
+pub struct RecordingProcessor {
+    pub lines: Vec<(String, bool)>,
+    pub passed_at: Vec<DateTime<Utc>>,
+    pub flushes: usize,
+    clock: Option<MockTimeProvider>,
+}
+
+impl RecordingProcessor {
+    pub fn with_clock(clock: MockTimeProvider) -> Self {
+        RecordingProcessor {
+            clock: Some(clock),
+            ..Default::default()
+        }
+    }
+}
+
+impl LogLineProcessor for RecordingProcessor {
+    fn process_line(&mut self, line: LogLine, is_first: bool) {
+        self.lines.push((line.body, is_first));
+        if let Some(clock) = &self.clock {
+            self.passed_at.push(clock.current_time());
+        }
+    }
+
+    fn flush(&mut self) {
+        self.flushes += 1;
+    }
+}
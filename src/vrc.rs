@@ -1,10 +1,217 @@
 pub mod log;
+pub mod osc;
 
-#[derive(Debug, PartialEq, Eq)]
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer};
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "event")]
 pub enum Event {
+    // Logged before loading the world, ahead of OnJoinedRoom.
+    EnteringRoom {
+        world_name: String,
+    },
     OnJoinedRoom,
-    OnPlayerJoined { user_name: String },
+    OnPlayerJoined {
+        #[serde(rename = "user")]
+        user_name: String,
+    },
     OnLeftRoom,
-    OnPlayerLeft { user_name: String },
-    UserAuthenticated { user_name: String },
+    OnPlayerLeft {
+        #[serde(rename = "user")]
+        user_name: String,
+    },
+    UserAuthenticated {
+        #[serde(rename = "user")]
+        user_name: String,
+    },
+    InstanceInfo {
+        // e.g. `wrld_...:12345~region(jp)`, which is the same while staying in the instance.
+        instance_id: String,
+        access_type: InstanceAccessType,
+        region: Option<Region>,
+    },
+    ApplicationQuit,
+    VideoPlaying {
+        url: String,
+        requested_by: Option<String>,
+    },
+    InstanceFull,
+    // The number of players in the instance, including the local one, which VRChat logs from time to time.
+    PlayerCount {
+        count: usize,
+    },
+    // A friend came online or went offline, wherever they are.
+    FriendPresence {
+        #[serde(rename = "user")]
+        user_name: String,
+        online: bool,
+    },
+    PhotoTaken {
+        path: String,
+    },
+    // The instance master moved to the player, e.g. after the previous one left.
+    MasterChanged {
+        #[serde(rename = "user")]
+        user_name: String,
+    },
+    // Either of them is None when the line does not tell.
+    PortalDropped {
+        #[serde(rename = "user")]
+        user_name: Option<String>,
+        world: Option<String>,
+    },
+    // A download of a world or an avatar has made no progress for a while, which often ends in a failed load.
+    // `what` is what is downloaded as logged, e.g. `World (The Great Pug)`.
+    AssetDownloadStalled {
+        what: String,
+    },
+    // `target` is None when the action is taken against the local user.
+    Moderation {
+        kind: ModerationKind,
+        target: Option<String>,
+    },
+    // Matched by a rule registered by the user, with its named groups.
+    Custom {
+        name: String,
+        captures: HashMap<String, String>,
+    },
+}
+
+impl Event {
+    // The name of the variant, which is also the `event` field of the JSON lines.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::EnteringRoom { .. } => "EnteringRoom",
+            Event::OnJoinedRoom => "OnJoinedRoom",
+            Event::OnPlayerJoined { .. } => "OnPlayerJoined",
+            Event::OnLeftRoom => "OnLeftRoom",
+            Event::OnPlayerLeft { .. } => "OnPlayerLeft",
+            Event::UserAuthenticated { .. } => "UserAuthenticated",
+            Event::InstanceInfo { .. } => "InstanceInfo",
+            Event::ApplicationQuit => "ApplicationQuit",
+            Event::VideoPlaying { .. } => "VideoPlaying",
+            Event::InstanceFull => "InstanceFull",
+            Event::PlayerCount { .. } => "PlayerCount",
+            Event::FriendPresence { .. } => "FriendPresence",
+            Event::PhotoTaken { .. } => "PhotoTaken",
+            Event::MasterChanged { .. } => "MasterChanged",
+            Event::PortalDropped { .. } => "PortalDropped",
+            Event::AssetDownloadStalled { .. } => "AssetDownloadStalled",
+            Event::Moderation { .. } => "Moderation",
+            Event::Custom { .. } => "Custom",
+        }
+    }
+}
+
+// An event with the log entry it was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEvent {
+    pub time: DateTime<Local>,
+    pub event: Event,
+    // The text of the entry following the time and the level, e.g. `[Behaviour] OnPlayerJoined Alice`.
+    pub raw: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum InstanceAccessType {
+    Public,
+    FriendsPlus,
+    Friends,
+    InvitePlus,
+    Invite,
+    Group,
+    Custom(String),
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum ModerationKind {
+    Kick,
+    Ban,
+    Timeout,
+    Warn,
+    Custom(String),
+}
+
+// The region of an instance, from the tag such as `region(jp)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Region {
+    // The code before the US East region was added, which is the same as `usw`.
+    Us,
+    UsWest,
+    UsEast,
+    Europe,
+    Japan,
+    Unknown(String),
+}
+
+impl Region {
+    pub fn from_code(code: &str) -> Region {
+        match code {
+            "us" => Region::Us,
+            "usw" => Region::UsWest,
+            "use" => Region::UsEast,
+            "eu" => Region::Europe,
+            "jp" => Region::Japan,
+            _ => Region::Unknown(code.to_owned()),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        match self {
+            Region::Us => "us",
+            Region::UsWest => "usw",
+            Region::UsEast => "use",
+            Region::Europe => "eu",
+            Region::Japan => "jp",
+            Region::Unknown(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Region::Us | Region::UsWest => write!(f, "US West"),
+            Region::UsEast => write!(f, "US East"),
+            Region::Europe => write!(f, "Europe"),
+            Region::Japan => write!(f, "Japan"),
+            Region::Unknown(code) => write!(f, "Unknown region ({})", code),
+        }
+    }
+}
+
+// Serialized as the code in the log, e.g. `"jp"`.
+impl Serialize for Region {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(self.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_displays_name_of_known_code() {
+        let names: Vec<String> = ["us", "usw", "use", "eu", "jp"]
+            .iter()
+            .map(|code| Region::from_code(code).to_string())
+            .collect();
+        assert_eq!(
+            vec!["US West", "US West", "US East", "Europe", "Japan"],
+            names
+        );
+    }
+
+    #[test]
+    fn region_falls_back_to_unknown_code() {
+        let region = Region::from_code("moon");
+        assert_eq!(Region::Unknown("moon".to_owned()), region);
+        assert_eq!("Unknown region (moon)", region.to_string());
+        assert_eq!("moon", region.code());
+    }
 }
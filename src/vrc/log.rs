@@ -1,41 +1,127 @@
+use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
 use std::fs;
 use std::io;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 
-use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Duration, Local, LocalResult, NaiveDateTime, Offset, TimeZone};
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use super::Event;
+use super::{Event, InstanceAccessType, ModerationKind, ParsedEvent, Region};
 
 lazy_static! {
-    pub static ref LOG_FILE_NAME_PATTERN: Regex = Regex::new(r"^output_log_.*\.txt$").unwrap();
+    pub static ref LOG_FILE_NAME_PATTERN: Regex =
+        Regex::new(r"^output_log_.*\.txt(\.gz)?$").unwrap();
+    static ref LOG_FILE_TIME_PATTERN: Regex =
+        Regex::new(r"^output_log_(?P<time>\d{4}-\d{2}-\d{2}_\d{2}-\d{2}-\d{2})\.txt(\.gz)?$")
+            .unwrap();
     static ref LOG_HEADER_PATTERN: Regex = Regex::new(
-        r"(?P<timestamp>\d{4}.\d{2}.\d{2} \d{2}:\d{2}:\d{2}) (?P<level>[^ ]+) *-  (?P<body>.*)"
+        r"(?P<timestamp>\d{4}.\d{2}.\d{2} \d{2}:\d{2}:\d{2}) (?P<level>[^ ]+) *-  (?P<body>(?s:.*))"
     )
     .unwrap();
+    static ref VRCHAT_BUILD_PATTERN: Regex =
+        Regex::new(r"\bVRChat Build: *(?P<version>\S.*?)\s*$").unwrap();
     static ref USER_AUTHENTICATED_PATTERN: Regex =
         Regex::new(r"\[Behaviour\] User Authenticated: (?P<username>\S+)").unwrap();
     static ref ON_JOINED_ROOM_PATTERN: Regex =
         Regex::new(r"\[Behaviour\] Finished entering world").unwrap();
-    static ref ON_PLAYER_JOINED_PATTERN: Regex =
-        Regex::new(r"\[Behaviour\] OnPlayerJoined (?P<username>.+?)($| \(usr_[a-z0-9-]+\))")
-            .unwrap();
+    // Only the user ID at the end of the line is stripped, since display names may contain
+    // spaces, parentheses or even `usr_`.
+    static ref ON_PLAYER_JOINED_PATTERN: Regex = Regex::new(
+        r"\[Behaviour\] OnPlayerJoined (?P<username>.+?)( \(usr_[0-9a-f]{8}(-[0-9a-f]{4}){3}-[0-9a-f]{12}\))?$"
+    )
+    .unwrap();
+    static ref ENTERING_ROOM_PATTERN: Regex =
+        Regex::new(r"^\[Behaviour\] Entering Room: (?P<world_name>.+)$").unwrap();
     static ref ON_LEFT_ROOM_PATTERN: Regex = Regex::new(r"\[Behaviour\] OnLeftRoom").unwrap();
-    static ref ON_PLAYER_LEFT_PATTERN: Regex =
-        Regex::new(r"\[Behaviour\] OnPlayerLeft (?P<username>.+?)($| \(usr_[a-z0-9-]+\))").unwrap();
+    static ref ON_PLAYER_LEFT_PATTERN: Regex = Regex::new(
+        r"\[Behaviour\] OnPlayerLeft (?P<username>.+?)( \(usr_[0-9a-f]{8}(-[0-9a-f]{4}){3}-[0-9a-f]{12}\))?$"
+    )
+    .unwrap();
+    static ref INSTANCE_INFO_PATTERN: Regex =
+        Regex::new(r"\[Behaviour\] Joining (?P<world_id>wrld_[^:\s]+):(?P<instance>\S+)").unwrap();
+    static ref APPLICATION_QUIT_PATTERN: Regex =
+        Regex::new(r"^(VRCApplication: |\[Behaviour\] )OnApplicationQuit").unwrap();
+    static ref VIDEO_RESOLVE_URL_PATTERN: Regex =
+        Regex::new(r"^\[Video Playback\] Attempting to resolve URL '(?P<url>[^']+)'").unwrap();
+    static ref VIDEO_ADD_URL_PATTERN: Regex =
+        Regex::new(r"User (?P<username>.+?) added URL (?P<url>\S+)$").unwrap();
+    static ref INSTANCE_FULL_PATTERN: Regex =
+        Regex::new(r"^\[Behaviour\] .*(?i:instance is full|room is full)").unwrap();
+    static ref MASTER_CHANGED_PATTERN: Regex = Regex::new(
+        r"^\[Behaviour\] (?:OnMasterClientSwitched|Master client switched to|Instance master changed to):? (?P<username>.+?)( \(usr_[0-9a-f]{8}(-[0-9a-f]{4}){3}-[0-9a-f]{12}\))?$"
+    )
+    .unwrap();
+    static ref PORTAL_DROPPED_PATTERN: Regex = Regex::new(
+        r"^\[Behaviour\] (?:(?P<username>.+?) )?(?i:dropped) (?:a )?portal(?: to (?P<world>.+?))?\.?$"
+    )
+    .unwrap();
+    // e.g. `[AssetBundleDownloadManager] [12] Download of World (The Great Pug) stalled`
+    static ref ASSET_DOWNLOAD_STALLED_PATTERN: Regex = Regex::new(
+        r"^\[AssetBundleDownloadManager\] (?:\[\d+\] )?Download(?:ing)?(?: of)? (?P<what>.+?) (?:has stalled|stalled|timed out|is taking too long)\.?$"
+    )
+    .unwrap();
+    static ref PLAYER_COUNT_PATTERN: Regex =
+        Regex::new(r"^\[Behaviour\] Player count: (?P<count>\d+)$").unwrap();
+    static ref FRIEND_PRESENCE_PATTERN: Regex = Regex::new(
+        r"^\[Behaviour\] Friend (?P<username>.+?) (is now|went) (?P<state>online|offline)\.?$"
+    )
+    .unwrap();
+    static ref PHOTO_TAKEN_PATTERN: Regex =
+        Regex::new(r"^\[VRC Camera\] Took screenshot to: (?P<path>.+)$").unwrap();
+    static ref MODERATION_PATTERN: Regex = Regex::new(
+        r"^\[(?P<source>ModerationManager|Behaviour)\] (You have been|(?P<target>.+?) has been) (?P<kind>[a-z]+( out)?)\b"
+    )
+    .unwrap();
+    static ref TEMPLATE_PLACEHOLDER_PATTERN: Regex = Regex::new(r"\{(?P<name>\w+)\}").unwrap();
+    static ref INSTANCE_TAG_PATTERN: Regex =
+        Regex::new(r"^(?P<name>[^()]+)(\((?P<value>[^()]*)\))?$").unwrap();
 }
 
 #[cfg(target_os = "windows")]
-pub fn get_log_dir_path() -> PathBuf {
-    let mut path = PathBuf::from(env::var(OsString::from("AppData")).unwrap());
-    path.pop();
+pub fn get_log_dir_path() -> io::Result<PathBuf> {
+    let app_data = env::var_os(OsString::from("AppData"));
+    let user_profile = env::var_os(OsString::from("USERPROFILE"));
+    log_dir_path_from(app_data, user_profile).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "neither AppData nor USERPROFILE is set",
+        )
+    })
+}
+
+// AppData points to the Roaming directory, which is next to LocalLow.
+fn log_dir_path_from(
+    app_data: Option<OsString>,
+    user_profile: Option<OsString>,
+) -> Option<PathBuf> {
+    let mut path = match (app_data, user_profile) {
+        (Some(app_data), _) => {
+            let mut path = PathBuf::from(app_data);
+            path.pop();
+            path
+        }
+        (None, Some(user_profile)) => {
+            let mut path = PathBuf::from(user_profile);
+            path.push("AppData");
+            path
+        }
+        (None, None) => return None,
+    };
     path.push("LocalLow");
     path.push("VRChat");
     path.push("vrchat");
-    path
+    Some(path)
+}
+
+// Newer builds embed the time the log was created, e.g. `output_log_2024-05-01_18-30-00.txt`.
+pub fn parse_log_file_time(file_name: &str) -> Option<NaiveDateTime> {
+    let cap = LOG_FILE_TIME_PATTERN.captures(file_name)?;
+    NaiveDateTime::parse_from_str(cap.name("time").unwrap().as_str(), "%Y-%m-%d_%H-%M-%S").ok()
 }
 
 pub fn get_log_entries<P>(log_dir: P) -> io::Result<impl Iterator<Item = fs::DirEntry>>
@@ -46,8 +132,9 @@ where
     let log_files = entries
         .filter_map(|entry| {
             let entry = entry.ok()?;
-            let file_type = entry.file_type().ok()?;
-            if file_type.is_file() {
+            // follow symlinks so that a linked log is read, unlike DirEntry::metadata.
+            let metadata = fs::metadata(entry.path()).ok()?;
+            if metadata.is_file() {
                 let name = entry.file_name().into_string().ok()?;
                 if LOG_FILE_NAME_PATTERN.is_match(&name) {
                     return Some(entry);
@@ -59,14 +146,175 @@ where
     Ok(log_files)
 }
 
-#[derive(Debug, PartialEq, Eq)]
+// Reads all the lines of an existing log at once, e.g. for a report over past logs.
+// The lines which can't be parsed, including the continuations of entries, are skipped.
+pub fn parse_log_file<P>(path: P) -> io::Result<Vec<LogLine>>
+where
+    P: AsRef<Path>,
+{
+    let reader = BufReader::new(fs::File::open(path)?);
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        if let Some(line) = LogLine::from_line(line?.trim_end()) {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
+
+// The build is logged within the first lines, so the rest of the log is not read.
+const VRCHAT_BUILD_SEARCH_LINES: usize = 200;
+
+// Reads the VRChat build from the head of the log, e.g. `2023.3.3p1-1355--Release`.
+pub fn read_vrchat_version<P>(path: P) -> io::Result<Option<String>>
+where
+    P: AsRef<Path>,
+{
+    let reader = BufReader::new(fs::File::open(path)?);
+    for line in reader.lines().take(VRCHAT_BUILD_SEARCH_LINES) {
+        if let Some(cap) = VRCHAT_BUILD_PATTERN.captures(&line?) {
+            return Ok(Some(cap.name("version").unwrap().as_str().to_owned()));
+        }
+    }
+    Ok(None)
+}
+
+// How to read a local time which is ambiguous or does not exist around a DST transition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmbiguousTimePolicy {
+    // The earlier of the instants in a fold. A time in a gap drops the line, as it always has.
+    #[default]
+    Earliest,
+    // The later of the instants in a fold, or the instant before a gap.
+    Latest,
+    // Drops the line.
+    Skip,
+}
+
+// A time in a fold has two instants. A time in a gap is read with the offset
+// before the gap only if the later instant is asked for.
+pub fn resolve_local_time<Tz: TimeZone>(
+    tz: &Tz,
+    time: &NaiveDateTime,
+    policy: AmbiguousTimePolicy,
+) -> Option<DateTime<Tz>> {
+    match tz.from_local_datetime(time) {
+        LocalResult::Single(time) => Some(time),
+        LocalResult::Ambiguous(earliest, latest) => match policy {
+            AmbiguousTimePolicy::Earliest => Some(earliest),
+            AmbiguousTimePolicy::Latest => Some(latest),
+            AmbiguousTimePolicy::Skip => None,
+        },
+        LocalResult::None => {
+            if policy != AmbiguousTimePolicy::Latest {
+                return None;
+            }
+            let offset = (1..=3).find_map(|n| {
+                tz.offset_from_local_datetime(&(*time - Duration::hours(n)))
+                    .single()
+                    .map(|offset| offset.fix())
+            })?;
+            let utc = *time - Duration::seconds(offset.local_minus_utc().into());
+            Some(tz.from_utc_datetime(&utc))
+        }
+    }
+}
+
+// Matches the lines which the built-in patterns don't, e.g. the output of a mod,
+// as Event::Custom with the named groups of the pattern.
+#[derive(Debug, Clone)]
+pub struct CustomRule {
+    pub name: String,
+    pub pattern: Regex,
+    // e.g. `{user} rolled {number}.`, where the placeholders are the names of the groups.
+    pub template: String,
+}
+
+impl CustomRule {
+    pub fn new(name: String, pattern: &str, template: String) -> Result<CustomRule, regex::Error> {
+        Ok(CustomRule {
+            name,
+            pattern: Regex::new(pattern)?,
+            template,
+        })
+    }
+
+    fn apply(&self, body: &str) -> Option<Event> {
+        let cap = self.pattern.captures(body)?;
+        let captures = self
+            .pattern
+            .capture_names()
+            .flatten()
+            .filter_map(|name| Some((name.to_owned(), cap.name(name)?.as_str().to_owned())))
+            .collect();
+        Some(Event::Custom {
+            name: self.name.to_owned(),
+            captures,
+        })
+    }
+}
+
+// The custom rules shared by the reader and the notifier,
+// which can be replaced while running, e.g. when the config file has changed.
+// The built-in patterns never change.
+#[derive(Debug, Clone, Default)]
+pub struct SharedCustomRules {
+    rules: Arc<RwLock<Vec<CustomRule>>>,
+}
+
+impl SharedCustomRules {
+    pub fn new(rules: Vec<CustomRule>) -> Self {
+        SharedCustomRules {
+            rules: Arc::new(RwLock::new(rules)),
+        }
+    }
+
+    // The lines read after this are matched against the new rules.
+    pub fn replace(&self, rules: Vec<CustomRule>) {
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Vec<CustomRule>> {
+        self.rules.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn template(&self, name: &str) -> Option<String> {
+        self.read()
+            .iter()
+            .find(|rule| rule.name == name)
+            .map(|rule| rule.template.to_owned())
+    }
+}
+
+impl From<Vec<CustomRule>> for SharedCustomRules {
+    fn from(rules: Vec<CustomRule>) -> Self {
+        SharedCustomRules::new(rules)
+    }
+}
+
+// Replaces the placeholders with the captures. A placeholder without the capture is kept as is.
+pub fn render_template(template: &str, captures: &HashMap<String, String>) -> String {
+    TEMPLATE_PLACEHOLDER_PATTERN
+        .replace_all(template, |cap: &regex::Captures| {
+            match captures.get(&cap["name"]) {
+                Some(value) => value.to_owned(),
+                None => cap[0].to_owned(),
+            }
+        })
+        .into_owned()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LogLevel {
+    Debug,
     Log,
     Warning,
     Error,
+    // e.g. `Exception` or `Assert`, which may still carry an event.
+    Other(String),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LogLine {
     pub time: DateTime<Local>,
     pub log_level: LogLevel,
@@ -75,19 +323,52 @@ pub struct LogLine {
 }
 
 impl LogLine {
+    // None if no event was found in the line.
+    pub fn into_parsed_event(self) -> Option<ParsedEvent> {
+        Some(ParsedEvent {
+            time: self.time,
+            event: self.event?,
+            raw: self.body,
+        })
+    }
+
     pub fn from_line(line: &str) -> Option<LogLine> {
+        Self::from_line_with_policy(line, AmbiguousTimePolicy::default())
+    }
+
+    pub fn from_line_with_policy(line: &str, policy: AmbiguousTimePolicy) -> Option<LogLine> {
+        Self::from_line_with_rules(line, policy, &[])
+    }
+
+    // The rules are tried in order only when none of the built-in patterns matches.
+    pub fn from_line_with_rules(
+        line: &str,
+        policy: AmbiguousTimePolicy,
+        rules: &[CustomRule],
+    ) -> Option<LogLine> {
+        Self::from_line_in(&Local, line, policy, rules)
+    }
+
+    fn from_line_in<Tz: TimeZone>(
+        tz: &Tz,
+        line: &str,
+        policy: AmbiguousTimePolicy,
+        rules: &[CustomRule],
+    ) -> Option<LogLine> {
         let cap = LOG_HEADER_PATTERN.captures(line)?;
         let timestamp = cap.name("timestamp").unwrap().as_str();
         let timestamp = NaiveDateTime::parse_from_str(timestamp, "%Y.%m.%d %H:%M:%S").ok()?;
-        let local_timestamp = Local.from_local_datetime(&timestamp).earliest()?;
+        let local_timestamp = resolve_local_time(tz, &timestamp, policy)?.with_timezone(&Local);
         let level = match cap.name("level").unwrap().as_str() {
+            "Debug" => LogLevel::Debug,
             "Log" => LogLevel::Log,
             "Warning" => LogLevel::Warning,
             "Error" => LogLevel::Error,
-            _ => return None,
+            level => LogLevel::Other(level.to_owned()),
         };
         let body = cap.name("body").unwrap().as_str();
-        let event = Self::parse_body(body);
+        let event =
+            Event::from_body(body).or_else(|| rules.iter().find_map(|rule| rule.apply(body)));
         Some(LogLine {
             time: local_timestamp,
             log_level: level,
@@ -96,7 +377,24 @@ impl LogLine {
         })
     }
 
-    fn parse_body(body: &str) -> Option<Event> {
+    // Lines without the header continue the previous entry, e.g. a stack trace.
+    pub fn is_header(line: &str) -> bool {
+        LOG_HEADER_PATTERN
+            .find(line)
+            .is_some_and(|m| m.start() == 0)
+    }
+}
+
+impl Event {
+    // Finds the event in the body of an entry, without its header, e.g. `[Behaviour] OnPlayerJoined Alice`.
+    // The custom rules are not tried.
+    pub fn from_body(body: &str) -> Option<Event> {
+        if let Some(cap) = ENTERING_ROOM_PATTERN.captures(body) {
+            return Some(Event::EnteringRoom {
+                world_name: cap.name("world_name").unwrap().as_str().to_owned(),
+            });
+        }
+
         if ON_JOINED_ROOM_PATTERN.is_match(body) {
             return Some(Event::OnJoinedRoom);
         }
@@ -123,10 +421,143 @@ impl LogLine {
             });
         }
 
+        if let Some(cap) = INSTANCE_INFO_PATTERN.captures(body) {
+            let instance = cap.name("instance").unwrap().as_str();
+            let (access_type, region) = parse_instance_tags(instance);
+            return Some(Event::InstanceInfo {
+                instance_id: format!("{}:{}", cap.name("world_id").unwrap().as_str(), instance),
+                access_type,
+                region,
+            });
+        }
+
+        if APPLICATION_QUIT_PATTERN.is_match(body) {
+            return Some(Event::ApplicationQuit);
+        }
+
+        if INSTANCE_FULL_PATTERN.is_match(body) {
+            return Some(Event::InstanceFull);
+        }
+
+        if let Some(cap) = PLAYER_COUNT_PATTERN.captures(body) {
+            if let Ok(count) = cap.name("count").unwrap().as_str().parse() {
+                return Some(Event::PlayerCount { count });
+            }
+        }
+
+        if let Some(cap) = FRIEND_PRESENCE_PATTERN.captures(body) {
+            return Some(Event::FriendPresence {
+                user_name: cap.name("username").unwrap().as_str().to_owned(),
+                online: cap.name("state").unwrap().as_str() == "online",
+            });
+        }
+
+        if let Some(cap) = PHOTO_TAKEN_PATTERN.captures(body) {
+            return Some(Event::PhotoTaken {
+                path: cap.name("path").unwrap().as_str().to_owned(),
+            });
+        }
+
+        if let Some(cap) = MASTER_CHANGED_PATTERN.captures(body) {
+            return Some(Event::MasterChanged {
+                user_name: cap.name("username").unwrap().as_str().to_owned(),
+            });
+        }
+
+        if let Some(cap) = PORTAL_DROPPED_PATTERN.captures(body) {
+            return Some(Event::PortalDropped {
+                user_name: cap.name("username").map(|u| u.as_str().to_owned()),
+                world: cap.name("world").map(|w| w.as_str().to_owned()),
+            });
+        }
+
+        if let Some(cap) = ASSET_DOWNLOAD_STALLED_PATTERN.captures(body) {
+            return Some(Event::AssetDownloadStalled {
+                what: cap.name("what").unwrap().as_str().to_owned(),
+            });
+        }
+
+        if let Some(cap) = MODERATION_PATTERN.captures(body) {
+            if let Some(kind) = parse_moderation_kind(
+                cap.name("source").unwrap().as_str(),
+                cap.name("kind").unwrap().as_str(),
+            ) {
+                return Some(Event::Moderation {
+                    kind,
+                    target: cap.name("target").map(|t| t.as_str().to_owned()),
+                });
+            }
+        }
+
+        if let Some(cap) = VIDEO_RESOLVE_URL_PATTERN.captures(body) {
+            return Some(Event::VideoPlaying {
+                url: cap.name("url").unwrap().as_str().to_owned(),
+                requested_by: None,
+            });
+        }
+
+        if let Some(cap) = VIDEO_ADD_URL_PATTERN.captures(body) {
+            return Some(Event::VideoPlaying {
+                url: cap.name("url").unwrap().as_str().to_owned(),
+                requested_by: Some(cap.name("username").unwrap().as_str().to_owned()),
+            });
+        }
+
         None
     }
 }
 
+// `[Behaviour]` lines are only trusted for the known actions since many unrelated lines
+// share the `... has been ...` wording.
+fn parse_moderation_kind(source: &str, kind: &str) -> Option<ModerationKind> {
+    match kind {
+        "kicked" => Some(ModerationKind::Kick),
+        "banned" => Some(ModerationKind::Ban),
+        "timed out" => Some(ModerationKind::Timeout),
+        "warned" => Some(ModerationKind::Warn),
+        _ if source == "ModerationManager" => Some(ModerationKind::Custom(kind.to_owned())),
+        _ => None,
+    }
+}
+
+// Parses the tags following the instance name, e.g. `12345~private(usr_...)~canRequestInvite~region(jp)`.
+// An instance without any access tag is a public instance.
+fn parse_instance_tags(instance: &str) -> (InstanceAccessType, Option<Region>) {
+    let mut access_type = None;
+    let mut can_request_invite = false;
+    let mut region = None;
+    for tag in instance.split('~').skip(1) {
+        let cap = match INSTANCE_TAG_PATTERN.captures(tag) {
+            Some(cap) => cap,
+            None => continue,
+        };
+        let value = cap.name("value").map(|v| v.as_str());
+        match cap.name("name").unwrap().as_str() {
+            "region" => region = value.map(Region::from_code),
+            "canRequestInvite" => can_request_invite = true,
+            // modifiers which do not change the access type.
+            "nonce" | "strict" | "groupAccessType" => (),
+            name => {
+                if access_type.is_none() {
+                    access_type = Some(match name {
+                        "hidden" => InstanceAccessType::FriendsPlus,
+                        "friends" => InstanceAccessType::Friends,
+                        "private" => InstanceAccessType::Invite,
+                        "group" => InstanceAccessType::Group,
+                        _ => InstanceAccessType::Custom(name.to_owned()),
+                    });
+                }
+            }
+        }
+    }
+    let access_type = match access_type {
+        Some(InstanceAccessType::Invite) if can_request_invite => InstanceAccessType::InvitePlus,
+        Some(access_type) => access_type,
+        None => InstanceAccessType::Public,
+    };
+    (access_type, region)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,6 +567,215 @@ mod tests {
         Local.from_local_datetime(&time).latest().unwrap()
     }
 
+    #[test]
+    fn event_from_body_classifies_each_event() {
+        let user = |name: &str| name.to_owned();
+        let cases =
+            vec![
+            (
+                "[Behaviour] Entering Room: The Great Pug",
+                Event::EnteringRoom {
+                    world_name: "The Great Pug".to_owned(),
+                },
+            ),
+            ("[Behaviour] Finished entering world.", Event::OnJoinedRoom),
+            (
+                "[Behaviour] OnPlayerJoined Alice",
+                Event::OnPlayerJoined {
+                    user_name: user("Alice"),
+                },
+            ),
+            ("[Behaviour] OnLeftRoom", Event::OnLeftRoom),
+            (
+                "[Behaviour] OnPlayerLeft Alice",
+                Event::OnPlayerLeft {
+                    user_name: user("Alice"),
+                },
+            ),
+            (
+                "[Behaviour] User Authenticated: Alice",
+                Event::UserAuthenticated {
+                    user_name: user("Alice"),
+                },
+            ),
+            (
+                "[Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(jp)",
+                Event::InstanceInfo {
+                    instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(jp)"
+                        .to_owned(),
+                    access_type: InstanceAccessType::Public,
+                    region: Some(Region::Japan),
+                },
+            ),
+            ("VRCApplication: OnApplicationQuit at 1.0", Event::ApplicationQuit),
+            ("[Behaviour] The instance is full.", Event::InstanceFull),
+            (
+                "[Behaviour] Player count: 12",
+                Event::PlayerCount { count: 12 },
+            ),
+            (
+                "[Behaviour] Friend Alice is now online.",
+                Event::FriendPresence {
+                    user_name: user("Alice"),
+                    online: true,
+                },
+            ),
+            (
+                r"[VRC Camera] Took screenshot to: C:\Pictures\VRChat_1.png",
+                Event::PhotoTaken {
+                    path: r"C:\Pictures\VRChat_1.png".to_owned(),
+                },
+            ),
+            (
+                "[Behaviour] OnMasterClientSwitched: Alice",
+                Event::MasterChanged {
+                    user_name: user("Alice"),
+                },
+            ),
+            (
+                "[Behaviour] Alice dropped a portal to The Great Pug.",
+                Event::PortalDropped {
+                    user_name: Some(user("Alice")),
+                    world: Some("The Great Pug".to_owned()),
+                },
+            ),
+            (
+                "[ModerationManager] Alice has been kicked from the instance.",
+                Event::Moderation {
+                    kind: ModerationKind::Kick,
+                    target: Some(user("Alice")),
+                },
+            ),
+            (
+                "[Video Playback] Attempting to resolve URL 'https://example.com/video'",
+                Event::VideoPlaying {
+                    url: "https://example.com/video".to_owned(),
+                    requested_by: None,
+                },
+            ),
+            (
+                "[USharpVideo] User Alice added URL https://example.com/video",
+                Event::VideoPlaying {
+                    url: "https://example.com/video".to_owned(),
+                    requested_by: Some(user("Alice")),
+                },
+            ),
+        ];
+        for (body, event) in cases {
+            assert_eq!(Some(event), Event::from_body(body), "{}", body);
+        }
+    }
+
+    #[test]
+    fn event_from_body_returns_none_for_unrelated_body() {
+        assert_eq!(None, Event::from_body("[Behaviour] Some unrelated message"));
+        assert_eq!(None, Event::from_body(""));
+    }
+
+    #[test]
+    fn log_dir_path_is_built_from_app_data() {
+        let actual = log_dir_path_from(
+            Some(OsString::from("C:/Users/paralleltree/AppData/Roaming")),
+            Some(OsString::from("C:/Users/someone")),
+        );
+        let expected = Path::new("C:/Users/paralleltree/AppData")
+            .join("LocalLow")
+            .join("VRChat")
+            .join("vrchat");
+        assert_eq!(Some(expected), actual);
+    }
+
+    #[test]
+    fn log_dir_path_falls_back_to_user_profile() {
+        let actual = log_dir_path_from(None, Some(OsString::from("C:/Users/paralleltree")));
+        let expected = Path::new("C:/Users/paralleltree")
+            .join("AppData")
+            .join("LocalLow")
+            .join("VRChat")
+            .join("vrchat");
+        assert_eq!(Some(expected), actual);
+    }
+
+    #[test]
+    fn log_dir_path_is_none_without_environment() {
+        assert_eq!(None, log_dir_path_from(None, None));
+    }
+
+    #[test]
+    fn parse_log_file_time_reads_dated_name() {
+        let expected = NaiveDate::from_ymd(2024, 5, 1).and_hms(18, 30, 0);
+        assert_eq!(
+            Some(expected),
+            parse_log_file_time("output_log_2024-05-01_18-30-00.txt")
+        );
+        assert_eq!(
+            Some(expected),
+            parse_log_file_time("output_log_2024-05-01_18-30-00.txt.gz")
+        );
+    }
+
+    #[test]
+    fn parse_log_file_time_ignores_undated_name() {
+        assert_eq!(None, parse_log_file_time("output_log_18-30-00.txt"));
+        assert_eq!(
+            None,
+            parse_log_file_time("output_log_2024-13-01_18-30-00.txt")
+        );
+        assert_eq!(None, parse_log_file_time("Player.log"));
+    }
+
+    #[test]
+    fn resolve_local_time_picks_instant_in_fold() {
+        let tz = chrono_tz::Europe::Berlin;
+        let time = NaiveDate::from_ymd(2021, 10, 31).and_hms(2, 30, 0);
+        let resolve = |policy| resolve_local_time(&tz, &time, policy).map(|t| t.naive_utc());
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2021, 10, 31).and_hms(0, 30, 0)),
+            resolve(AmbiguousTimePolicy::Earliest)
+        );
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2021, 10, 31).and_hms(1, 30, 0)),
+            resolve(AmbiguousTimePolicy::Latest)
+        );
+        assert_eq!(None, resolve(AmbiguousTimePolicy::Skip));
+    }
+
+    #[test]
+    fn resolve_local_time_picks_instant_in_gap() {
+        let tz = chrono_tz::Europe::Berlin;
+        let time = NaiveDate::from_ymd(2021, 3, 28).and_hms(2, 30, 0);
+        let resolve = |policy| resolve_local_time(&tz, &time, policy).map(|t| t.naive_utc());
+        assert_eq!(None, resolve(AmbiguousTimePolicy::Earliest));
+        assert_eq!(
+            Some(NaiveDate::from_ymd(2021, 3, 28).and_hms(1, 30, 0)),
+            resolve(AmbiguousTimePolicy::Latest)
+        );
+        assert_eq!(None, resolve(AmbiguousTimePolicy::Skip));
+    }
+
+    #[test]
+    fn log_line_drops_time_in_gap_by_default() {
+        let line = "2021.03.28 02:30:00 Log        -  [Behaviour] OnPlayerJoined paralleltree";
+        let tz = chrono_tz::Europe::Berlin;
+        assert!(LogLine::from_line_in(&tz, line, Default::default(), &[]).is_none());
+    }
+
+    #[test]
+    fn resolve_local_time_keeps_unambiguous_time() {
+        let tz = chrono_tz::Europe::Berlin;
+        let time = NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 23, 12);
+        for policy in [
+            AmbiguousTimePolicy::Earliest,
+            AmbiguousTimePolicy::Latest,
+            AmbiguousTimePolicy::Skip,
+        ] {
+            assert_eq!(
+                Some(NaiveDate::from_ymd(2021, 12, 1).and_hms(22, 23, 12)),
+                resolve_local_time(&tz, &time, policy).map(|t| t.naive_utc())
+            );
+        }
+    }
+
     #[test]
     fn log_line_can_parse_on_joined_room_event() {
         let line = "2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.";
@@ -149,6 +789,21 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn log_line_can_parse_entering_room_event() {
+        let line = "2021.12.01 23:23:05 Log        -  [Behaviour] Entering Room: The Great Pug";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 23, 5)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::EnteringRoom {
+                world_name: "The Great Pug".to_owned(),
+            }),
+            body: "[Behaviour] Entering Room: The Great Pug".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
     #[test]
     fn log_line_can_parse_old_on_player_joined_event() {
         let line = "2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree";
@@ -180,6 +835,40 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn log_line_keeps_special_characters_in_user_names() {
+        for name in [
+            "para llel tree",
+            "(╯°□°)╯",
+            "tree (usr_friend)",
+            "パラレル(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)",
+        ] {
+            for (event, expected) in [
+                (
+                    "OnPlayerJoined",
+                    crate::vrc::Event::OnPlayerJoined {
+                        user_name: name.to_owned(),
+                    },
+                ),
+                (
+                    "OnPlayerLeft",
+                    crate::vrc::Event::OnPlayerLeft {
+                        user_name: name.to_owned(),
+                    },
+                ),
+            ] {
+                for suffix in ["", " (usr_a58186d2-54f9-44c8-902b-6e03927f66c1)"] {
+                    let line = format!(
+                        "2021.12.01 23:23:13 Log        -  [Behaviour] {} {}{}",
+                        event, name, suffix
+                    );
+                    let actual = LogLine::from_line(&line).expect("could not parse log line.");
+                    assert_eq!(Some(&expected), actual.event.as_ref(), "{}", line);
+                }
+            }
+        }
+    }
+
     #[test]
     fn log_line_can_parse_on_left_room_event() {
         let line = "2021.12.01 23:26:39 Log        -  [Behaviour] OnLeftRoom";
@@ -238,4 +927,548 @@ mod tests {
         };
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn log_line_can_parse_public_instance_info_event() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(us)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 23, 2)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(us)"
+                    .to_owned(),
+                access_type: InstanceAccessType::Public,
+                region: Some(Region::Us),
+            }),
+            body: "[Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(us)"
+                .to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_friends_instance_info_event() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~friends(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(jp)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~friends(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(jp)".to_owned(),
+                access_type: InstanceAccessType::Friends,
+                region: Some(Region::Japan),
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_friends_plus_instance_info_event() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~hidden(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(eu)~nonce(deadbeef)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~hidden(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(eu)~nonce(deadbeef)".to_owned(),
+                access_type: InstanceAccessType::FriendsPlus,
+                region: Some(Region::Europe),
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_invite_instance_info_event() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~private(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~private(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)".to_owned(),
+                access_type: InstanceAccessType::Invite,
+                region: None,
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_invite_plus_instance_info_event() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~private(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~canRequestInvite~region(jp)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~private(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~canRequestInvite~region(jp)".to_owned(),
+                access_type: InstanceAccessType::InvitePlus,
+                region: Some(Region::Japan),
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_unknown_instance_access_type() {
+        let line = "2021.12.01 23:23:02 Log        -  [Behaviour] Joining wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~somethingNew(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(jp)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::InstanceInfo {
+                instance_id: "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~somethingNew(usr_a58186d2-54f9-44c8-902b-6e03927f66c1)~region(jp)".to_owned(),
+                access_type: InstanceAccessType::Custom("somethingNew".to_owned()),
+                region: Some(Region::Japan),
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_application_quit_event() {
+        let line =
+            "2021.12.01 23:59:02 Log        -  VRCApplication: OnApplicationQuit at 2283.7397";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 59, 2)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::ApplicationQuit),
+            body: "VRCApplication: OnApplicationQuit at 2283.7397".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_video_resolve_event() {
+        let line = "2021.12.01 23:30:05 Log        -  [Video Playback] Attempting to resolve URL 'https://www.youtube.com/watch?v=dQw4w9WgXcQ'";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 30, 5)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::VideoPlaying {
+                url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned(),
+                requested_by: None,
+            }),
+            body: "[Video Playback] Attempting to resolve URL 'https://www.youtube.com/watch?v=dQw4w9WgXcQ'".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_video_add_event() {
+        let line = "2021.12.01 23:30:04 Log        -  [Video Playback] User paralleltree added URL https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::VideoPlaying {
+                url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned(),
+                requested_by: Some("paralleltree".to_owned()),
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_does_not_parse_other_video_playback_lines() {
+        for line in [
+            "2021.12.01 23:30:06 Log        -  [Video Playback] URL 'https://www.youtube.com/watch?v=dQw4w9WgXcQ' resolved to 'https://rr2---sn.googlevideo.com/videoplayback'",
+            "2021.12.01 23:30:06 Error      -  [Video Playback] ERROR: Video unavailable",
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(None, actual.event);
+        }
+    }
+
+    #[test]
+    fn log_line_can_parse_instance_full_event() {
+        let line = "2021.12.01 23:40:10 Log        -  [Behaviour] Instance is full, cannot accept more players (32/32)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 40, 10)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::InstanceFull),
+            body: "[Behaviour] Instance is full, cannot accept more players (32/32)".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_converts_into_parsed_event() {
+        let line = "2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree (usr_a58186d2-54f9-44c8-902b-6e03927f66c1)";
+        let actual = LogLine::from_line(line)
+            .expect("could not parse log line.")
+            .into_parsed_event();
+        let expected = ParsedEvent {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 23, 13)),
+            event: crate::vrc::Event::OnPlayerJoined {
+                user_name: "paralleltree".to_owned(),
+            },
+            raw:
+                "[Behaviour] OnPlayerJoined paralleltree (usr_a58186d2-54f9-44c8-902b-6e03927f66c1)"
+                    .to_owned(),
+        };
+        assert_eq!(Some(expected), actual);
+
+        let line = "2021.12.01 23:23:14 Log        -  [Behaviour] Some unrelated message";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(None, actual.into_parsed_event());
+    }
+
+    #[test]
+    fn log_line_can_parse_master_changed_event() {
+        for body in [
+            "[Behaviour] OnMasterClientSwitched paralleltree",
+            "[Behaviour] Master client switched to paralleltree (usr_a58186d2-54f9-44c8-902b-6e03927f66c1)",
+            "[Behaviour] Instance master changed to paralleltree",
+        ] {
+            let line = format!("2021.12.01 23:40:10 Log        -  {}", body);
+            let actual = LogLine::from_line(&line).expect("could not parse log line.");
+            assert_eq!(
+                Some(crate::vrc::Event::MasterChanged {
+                    user_name: "paralleltree".to_owned(),
+                }),
+                actual.event
+            );
+        }
+    }
+
+    #[test]
+    fn log_line_can_parse_portal_dropped_event() {
+        let line = "2021.12.01 23:40:10 Log        -  [Behaviour] paralleltree dropped a portal to The Great Pug";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::PortalDropped {
+                user_name: Some("paralleltree".to_owned()),
+                world: Some("The Great Pug".to_owned()),
+            }),
+            actual.event
+        );
+
+        let line = "2021.12.01 23:40:10 Log        -  [Behaviour] Dropped portal";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::PortalDropped {
+                user_name: None,
+                world: None,
+            }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_asset_download_stalled_event() {
+        for (line, what) in [
+            (
+                "2021.12.01 23:40:10 Warning    -  [AssetBundleDownloadManager] [12] Download of World (The Great Pug) stalled",
+                "World (The Great Pug)",
+            ),
+            (
+                "2021.12.01 23:40:10 Warning    -  [AssetBundleDownloadManager] Downloading Avatar (Pug) timed out.",
+                "Avatar (Pug)",
+            ),
+            (
+                "2021.12.01 23:40:10 Warning    -  [AssetBundleDownloadManager] [3] Download of World (The Great Pug) is taking too long",
+                "World (The Great Pug)",
+            ),
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(
+                Some(crate::vrc::Event::AssetDownloadStalled {
+                    what: what.to_owned(),
+                }),
+                actual.event
+            );
+        }
+
+        // the progress of a download is not a warning.
+        let line = "2021.12.01 23:40:10 Log        -  [AssetBundleDownloadManager] [12] Unpacking World (The Great Pug)";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(None, actual.event);
+    }
+
+    #[test]
+    fn log_line_does_not_parse_other_lines_as_portal_dropped() {
+        for line in [
+            "2021.12.01 23:40:10 Log        -  [Behaviour] paralleltree dropped a pickup",
+            "2021.12.01 23:40:10 Log        -  [Behaviour] Portal closed",
+            "2021.12.01 23:40:10 Log        -  [Network] paralleltree dropped a portal to The Great Pug",
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(None, actual.event);
+        }
+    }
+
+    #[test]
+    fn log_line_can_parse_player_count_event() {
+        let line = "2021.12.01 23:40:10 Log        -  [Behaviour] Player count: 12";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        assert_eq!(
+            Some(crate::vrc::Event::PlayerCount { count: 12 }),
+            actual.event
+        );
+    }
+
+    #[test]
+    fn log_line_can_parse_moderation_event_against_local_user() {
+        let line = "2021.12.01 23:45:00 Log        -  [ModerationManager] You have been kicked from the instance by the instance owner.";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 45, 0)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::Moderation {
+                kind: ModerationKind::Kick,
+                target: None,
+            }),
+            body:
+                "[ModerationManager] You have been kicked from the instance by the instance owner."
+                    .to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_moderation_event_against_other_user() {
+        for (line, kind) in [
+            (
+                "2021.12.01 23:45:00 Log        -  [Behaviour] paralleltree has been banned from the instance.",
+                ModerationKind::Ban,
+            ),
+            (
+                "2021.12.01 23:45:00 Log        -  [ModerationManager] paralleltree has been timed out for 5 minutes.",
+                ModerationKind::Timeout,
+            ),
+            (
+                "2021.12.01 23:45:00 Log        -  [ModerationManager] paralleltree has been warned by the instance owner.",
+                ModerationKind::Warn,
+            ),
+            (
+                "2021.12.01 23:45:00 Log        -  [ModerationManager] paralleltree has been muted by the instance owner.",
+                ModerationKind::Custom("muted".to_owned()),
+            ),
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(
+                Some(crate::vrc::Event::Moderation {
+                    kind,
+                    target: Some("paralleltree".to_owned()),
+                }),
+                actual.event
+            );
+        }
+    }
+
+    #[test]
+    fn log_line_does_not_parse_unrelated_behaviour_lines_as_moderation() {
+        for line in [
+            "2021.12.01 23:45:00 Log        -  [Behaviour] Avatar has been loaded for paralleltree",
+            "2021.12.01 23:45:00 Log        -  [Behaviour] Local player has been initialized",
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(None, actual.event);
+        }
+    }
+
+    #[test]
+    fn log_line_can_parse_event_with_unknown_log_level() {
+        let line = "2021.12.01 23:23:13 Exception  -  [Behaviour] OnPlayerJoined paralleltree";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 23, 13)),
+            log_level: LogLevel::Other("Exception".to_owned()),
+            event: Some(crate::vrc::Event::OnPlayerJoined {
+                user_name: "paralleltree".to_owned(),
+            }),
+            body: "[Behaviour] OnPlayerJoined paralleltree".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_friend_online_event() {
+        let line =
+            "2021.12.01 23:50:00 Log        -  [Behaviour] Friend para llel tree is now online";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 50, 0)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::FriendPresence {
+                user_name: "para llel tree".to_owned(),
+                online: true,
+            }),
+            body: "[Behaviour] Friend para llel tree is now online".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_friend_offline_event() {
+        for line in [
+            "2021.12.01 23:55:00 Log        -  [Behaviour] Friend paralleltree is now offline",
+            "2021.12.01 23:55:00 Log        -  [Behaviour] Friend paralleltree went offline.",
+        ] {
+            let actual = LogLine::from_line(line).expect("could not parse log line.");
+            assert_eq!(
+                Some(crate::vrc::Event::FriendPresence {
+                    user_name: "paralleltree".to_owned(),
+                    online: false,
+                }),
+                actual.event
+            );
+        }
+    }
+
+    #[test]
+    fn log_line_can_parse_photo_taken_event() {
+        let line = "2021.12.01 23:58:00 Log        -  [VRC Camera] Took screenshot to: C:\\Users\\paralleltree\\Pictures\\VRChat\\2021-12\\VRChat_2021-12-01_23-58-00.123_1920x1080.png";
+        let actual = LogLine::from_line(line).expect("could not parse log line.");
+        let expected = LogLine {
+            time: local_time(&NaiveDate::from_ymd(2021, 12, 1).and_hms(23, 58, 0)),
+            log_level: LogLevel::Log,
+            event: Some(crate::vrc::Event::PhotoTaken {
+                path: "C:\\Users\\paralleltree\\Pictures\\VRChat\\2021-12\\VRChat_2021-12-01_23-58-00.123_1920x1080.png".to_owned(),
+            }),
+            body: "[VRC Camera] Took screenshot to: C:\\Users\\paralleltree\\Pictures\\VRChat\\2021-12\\VRChat_2021-12-01_23-58-00.123_1920x1080.png".to_owned(),
+        };
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn log_line_can_parse_custom_event() {
+        let rules = vec![CustomRule::new(
+            "dice".to_owned(),
+            r"^\[DiceMod\] (?P<user>.+) rolled (?P<number>\d+)(?: \((?P<note>.+)\))?$",
+            "{user} rolled {number}.".to_owned(),
+        )
+        .unwrap()];
+        let line = "2021.12.01 23:23:12 Log        -  [DiceMod] paralleltree rolled 6";
+        let actual = LogLine::from_line_with_rules(line, AmbiguousTimePolicy::default(), &rules)
+            .expect("could not parse log line.");
+        let expected = crate::vrc::Event::Custom {
+            name: "dice".to_owned(),
+            captures: HashMap::from([
+                ("user".to_owned(), "paralleltree".to_owned()),
+                ("number".to_owned(), "6".to_owned()),
+            ]),
+        };
+        assert_eq!(Some(expected), actual.event);
+
+        // the built-in patterns come first.
+        let rules =
+            vec![CustomRule::new("left".to_owned(), r"OnLeftRoom", "left".to_owned()).unwrap()];
+        let line = "2021.12.01 23:23:12 Log        -  [Behaviour] OnLeftRoom";
+        let actual =
+            LogLine::from_line_with_rules(line, AmbiguousTimePolicy::default(), &rules).unwrap();
+        assert_eq!(Some(crate::vrc::Event::OnLeftRoom), actual.event);
+    }
+
+    #[test]
+    fn render_template_fills_captures() {
+        let captures = HashMap::from([
+            ("user".to_owned(), "paralleltree".to_owned()),
+            ("number".to_owned(), "6".to_owned()),
+        ]);
+        assert_eq!(
+            "paralleltree rolled 6 {note}",
+            render_template("{user} rolled {number} {note}", &captures)
+        );
+    }
+
+    #[test]
+    fn get_log_entries_skips_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("output_log_23-23-00.txt"), "").unwrap();
+        fs::create_dir(dir.path().join("output_log_23-24-00.txt")).unwrap();
+        let names = get_log_entries(dir.path())
+            .unwrap()
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![OsString::from("output_log_23-23-00.txt")], names);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn get_log_entries_follows_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("archive.txt");
+        fs::write(&target, "").unwrap();
+        std::os::unix::fs::symlink(&target, dir.path().join("output_log_23-23-00.txt")).unwrap();
+        // a link to a directory is still skipped.
+        let target_dir = dir.path().join("archive");
+        fs::create_dir(&target_dir).unwrap();
+        std::os::unix::fs::symlink(&target_dir, dir.path().join("output_log_23-24-00.txt"))
+            .unwrap();
+        let names = get_log_entries(dir.path())
+            .unwrap()
+            .map(|entry| entry.file_name())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![OsString::from("output_log_23-23-00.txt")], names);
+    }
+
+    #[test]
+    fn parse_log_file_reads_all_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(
+            &path,
+            "\
+2021.12.01 23:23:12 Log        -  [Behaviour] Finished entering world.
+
+2021.12.01 23:23:13 Log        -  [Behaviour] OnPlayerJoined paralleltree
+
+2021.12.01 23:23:14 Error      -  NullReferenceException: Object reference not set
+  at VRC.Core.ApiWorld.Fetch () [0x00000] in <00000000000000000000000000000000>:0
+
+2021.12.01 23:26:39 Log        -  [Behaviour] OnPlayerLeft paralleltree
+",
+        )
+        .unwrap();
+        let lines = parse_log_file(&path).unwrap();
+        assert_eq!(
+            vec![
+                Some(crate::vrc::Event::OnJoinedRoom),
+                Some(crate::vrc::Event::OnPlayerJoined {
+                    user_name: "paralleltree".to_owned()
+                }),
+                None,
+                Some(crate::vrc::Event::OnPlayerLeft {
+                    user_name: "paralleltree".to_owned()
+                }),
+            ],
+            lines.into_iter().map(|line| line.event).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn read_vrchat_version_finds_build_in_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        fs::write(
+            &path,
+            "\
+2021.12.01 23:20:00 Log        -  Environment Info:
+2021.12.01 23:20:00 Log        -  Operating System: Windows 10  (10.0.19044) 64bit
+2021.12.01 23:20:00 Log        -  VRChat Build: 2021.4.2p2-1155--Release\r
+
+2021.12.01 23:20:01 Log        -  [Behaviour] User Authenticated: paralleltree
+",
+        )
+        .unwrap();
+        assert_eq!(
+            Some("2021.4.2p2-1155--Release".to_owned()),
+            read_vrchat_version(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_vrchat_version_is_none_without_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output_log_23-23-00.txt");
+        let mut text = String::new();
+        for _ in 0..VRCHAT_BUILD_SEARCH_LINES {
+            text.push_str("2021.12.01 23:20:00 Log        -  [Behaviour] OnLeftRoom\n");
+        }
+        // past the head of the log.
+        text.push_str("2021.12.01 23:20:00 Log        -  VRChat Build: 2021.4.2p2-1155--Release\n");
+        fs::write(&path, text).unwrap();
+        assert_eq!(None, read_vrchat_version(&path).unwrap());
+    }
+
+    #[test]
+    fn parse_log_file_fails_for_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = parse_log_file(dir.path().join("output_log_23-23-00.txt")).unwrap_err();
+        assert_eq!(io::ErrorKind::NotFound, error.kind());
+    }
 }
@@ -0,0 +1,152 @@
+use std::cell::{Cell, RefCell};
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::xsoverlay::{MessageObject, NotificationSink, SendMessageError};
+
+const CHATBOX_INPUT_ADDRESS: &str = "/chatbox/input";
+// VRChat drops the characters beyond this length.
+const CHATBOX_MAX_CHARS: usize = 144;
+
+// OSC strings are null-terminated and padded to a multiple of 4 bytes.
+fn write_osc_string(buf: &mut Vec<u8>, s: &str) {
+    buf.extend_from_slice(s.as_bytes());
+    buf.push(0);
+    while !buf.len().is_multiple_of(4) {
+        buf.push(0);
+    }
+}
+
+// Encodes `/chatbox/input` with the text and the flag to send it without opening the keyboard.
+pub fn encode_chatbox_input(text: &str, immediate: bool) -> Vec<u8> {
+    let text: String = text.chars().take(CHATBOX_MAX_CHARS).collect();
+    let mut buf = Vec::new();
+    write_osc_string(&mut buf, CHATBOX_INPUT_ADDRESS);
+    write_osc_string(&mut buf, if immediate { ",sT" } else { ",sF" });
+    write_osc_string(&mut buf, &text);
+    buf
+}
+
+pub struct OscChatboxClient {
+    socket: UdpSocket,
+    endpoint: String,
+}
+
+impl OscChatboxClient {
+    pub fn new() -> Result<OscChatboxClient, io::Error> {
+        Self::new_with_endpoint("127.0.0.1", 9000)
+    }
+
+    pub fn new_with_endpoint(host: &str, port: u16) -> Result<OscChatboxClient, io::Error> {
+        let socket = UdpSocket::bind("127.0.0.1:0")?;
+        Ok(OscChatboxClient {
+            socket,
+            endpoint: format!("{}:{}", host, port),
+        })
+    }
+
+    pub fn send_text(&self, text: &str) -> io::Result<()> {
+        let packet = encode_chatbox_input(text, true);
+        self.socket.send_to(&packet, &self.endpoint)?;
+        Ok(())
+    }
+}
+
+// Writes notifications into the chatbox, keeping VRChat's rate limit.
+// A message arriving too early is held, and only the latest one is sent on flush.
+pub struct OscChatboxSink {
+    client: OscChatboxClient,
+    min_interval: Duration,
+    last_sent: Cell<Option<Instant>>,
+    pending: RefCell<Option<String>>,
+}
+
+impl OscChatboxSink {
+    pub fn new(client: OscChatboxClient) -> OscChatboxSink {
+        OscChatboxSink {
+            client,
+            min_interval: Duration::from_millis(1500),
+            last_sent: Cell::new(None),
+            pending: RefCell::new(None),
+        }
+    }
+
+    fn is_throttled(&self) -> bool {
+        match self.last_sent.get() {
+            Some(last_sent) => last_sent.elapsed() < self.min_interval,
+            None => false,
+        }
+    }
+
+    fn send_text(&self, text: &str) -> Result<(), SendMessageError> {
+        self.last_sent.set(Some(Instant::now()));
+        self.client.send_text(text)?;
+        Ok(())
+    }
+}
+
+impl NotificationSink for OscChatboxSink {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        let text = if message.content().is_empty() {
+            message.title().to_owned()
+        } else {
+            format!("{} {}", message.title(), message.content())
+        };
+        if self.is_throttled() {
+            self.pending.replace(Some(text));
+            return Ok(());
+        }
+        self.pending.replace(None);
+        self.send_text(&text)
+    }
+
+    fn flush(&self) -> Result<(), SendMessageError> {
+        if self.is_throttled() {
+            return Ok(());
+        }
+        match self.pending.take() {
+            Some(text) => self.send_text(&text),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_chatbox_input_pads_each_part() {
+        let expected: Vec<u8> = [
+            &b"/chatbox/input\0\0"[..],
+            &b",sT\0"[..],
+            &b"Alice joined.\0\0\0"[..],
+        ]
+        .concat();
+        assert_eq!(expected, encode_chatbox_input("Alice joined.", true));
+    }
+
+    #[test]
+    fn encode_chatbox_input_adds_padding_word_for_aligned_text() {
+        let expected: Vec<u8> = [
+            &b"/chatbox/input\0\0"[..],
+            &b",sF\0"[..],
+            &b"Bob left\0\0\0\0"[..],
+        ]
+        .concat();
+        assert_eq!(expected, encode_chatbox_input("Bob left", false));
+    }
+
+    #[test]
+    fn encode_chatbox_input_truncates_long_text() {
+        let text = "あ".repeat(200);
+        let packet = encode_chatbox_input(&text, true);
+        let body = &packet[20..];
+        let end = body.iter().position(|b| *b == 0).unwrap();
+        assert_eq!(
+            "あ".repeat(144),
+            String::from_utf8(body[..end].to_vec()).unwrap()
+        );
+    }
+}
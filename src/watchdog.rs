@@ -0,0 +1,162 @@
+use std::io;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::notifier::CurrentTimeProvider;
+use crate::xsoverlay::{MessageDefaults, MessageObjectBuilder, NotificationSink, NotificationType};
+
+// Warns once when no log has been found for the grace period, e.g. VRChat is not running,
+// and again only after a log has been read in between.
+pub struct MissingLogWatchdog<C, S>
+where
+    C: CurrentTimeProvider,
+    S: NotificationSink,
+{
+    client: S,
+    grace_period: Duration,
+    message_defaults: MessageDefaults,
+    missing_since: Option<DateTime<Utc>>,
+    warned: bool,
+    current_time_provider: C,
+}
+
+impl<C: CurrentTimeProvider, S: NotificationSink> MissingLogWatchdog<C, S> {
+    pub fn new(client: S, current_time_provider: C, grace_period: Duration) -> Self {
+        MissingLogWatchdog {
+            client,
+            grace_period,
+            message_defaults: MessageDefaults::default(),
+            missing_since: None,
+            warned: false,
+            current_time_provider,
+        }
+    }
+
+    // The warning starts from these, as the notifications of the events do.
+    pub fn set_message_defaults(&mut self, message_defaults: MessageDefaults) {
+        self.message_defaults = message_defaults;
+    }
+
+    // Takes the result of VrChatLogProcessor::process_log, which fails with NotFound without logs.
+    // Other errors tell nothing about the logs, so they are ignored.
+    pub fn observe(&mut self, result: &io::Result<()>) {
+        match result {
+            Ok(()) => self.record_found(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.record_missing(),
+            Err(_) => (),
+        }
+    }
+
+    pub fn record_found(&mut self) {
+        self.missing_since = None;
+        self.warned = false;
+    }
+
+    pub fn record_missing(&mut self) {
+        let now = self.current_time_provider.current_time();
+        let missing_since = *self.missing_since.get_or_insert(now);
+        if self.warned || now - missing_since < self.grace_period {
+            return;
+        }
+        self.warned = true;
+        let message = MessageObjectBuilder::with_defaults(
+            "No VRChat logs found — is VRChat running?".to_owned(),
+            &self.message_defaults,
+        )
+        .set_icon(NotificationType::Warning, false)
+        .set_timeout(3f32)
+        .build();
+        if let Err(e) = self.client.send_message(&message) {
+            eprintln!("{}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::{MockTimeProvider, RecordingSink};
+
+    fn watchdog() -> (
+        MissingLogWatchdog<MockTimeProvider, RecordingSink>,
+        MockTimeProvider,
+        RecordingSink,
+    ) {
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let watchdog = MissingLogWatchdog::new(sink.clone(), clock.clone(), Duration::seconds(60));
+        (watchdog, clock, sink)
+    }
+
+    fn not_found() -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    #[test]
+    fn watchdog_warns_once_after_grace_period() {
+        let (mut watchdog, clock, sink) = watchdog();
+        for _ in 0..59 {
+            watchdog.observe(&not_found());
+            clock.advance(Duration::seconds(1));
+        }
+        assert!(sink.titles().is_empty());
+
+        for _ in 0..120 {
+            watchdog.observe(&not_found());
+            clock.advance(Duration::seconds(1));
+        }
+        assert_eq!(
+            vec!["No VRChat logs found — is VRChat running?"],
+            sink.titles()
+        );
+    }
+
+    #[test]
+    fn watchdog_starts_over_after_log_is_read() {
+        let (mut watchdog, clock, sink) = watchdog();
+        watchdog.observe(&not_found());
+        clock.advance(Duration::seconds(50));
+        watchdog.observe(&Ok(()));
+        clock.advance(Duration::seconds(1));
+        watchdog.observe(&not_found());
+        clock.advance(Duration::seconds(50));
+        watchdog.observe(&not_found());
+        assert!(sink.titles().is_empty());
+
+        clock.advance(Duration::seconds(10));
+        watchdog.observe(&not_found());
+        watchdog.observe(&Ok(()));
+        watchdog.observe(&not_found());
+        clock.advance(Duration::seconds(60));
+        watchdog.observe(&not_found());
+        assert_eq!(2, sink.titles().len());
+    }
+
+    #[test]
+    fn watchdog_warns_with_message_defaults() {
+        let (mut watchdog, clock, sink) = watchdog();
+        watchdog.set_message_defaults(MessageDefaults {
+            source_app: "vrc_doorkeeper-alt".to_owned(),
+            ..Default::default()
+        });
+        watchdog.observe(&not_found());
+        clock.advance(Duration::seconds(60));
+        watchdog.observe(&not_found());
+        let json: serde_json::Value =
+            serde_json::from_str(&sink.messages.borrow()[0].to_json().unwrap()).unwrap();
+        assert_eq!("vrc_doorkeeper-alt", json["sourceApp"]);
+        assert_eq!("warning", json["icon"]);
+    }
+
+    #[test]
+    fn watchdog_ignores_other_errors() {
+        let (mut watchdog, clock, sink) = watchdog();
+        watchdog.observe(&not_found());
+        clock.advance(Duration::seconds(60));
+        watchdog.observe(&Err(io::Error::from(io::ErrorKind::PermissionDenied)));
+        assert!(sink.titles().is_empty());
+        watchdog.observe(&not_found());
+        assert_eq!(1, sink.titles().len());
+    }
+}
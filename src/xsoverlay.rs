@@ -1,10 +1,18 @@
+use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
 use std::io;
-use std::net::UdpSocket;
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::rc::Rc;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::Serialize;
 use serde_repr::Serialize_repr;
 
-#[derive(Serialize, Debug)]
+use crate::notifier::CurrentTimeProvider;
+
+#[derive(Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageObject {
     message_type: MessageType,
@@ -31,42 +39,143 @@ impl MessageObject {
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(&self)
     }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn timeout(&self) -> f32 {
+        self.timeout
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+}
+
+// What a message starts with before the builder modifies it.
+#[derive(Debug, Clone)]
+pub struct MessageDefaults {
+    pub timeout: f32,
+    pub height: f32,
+    pub opacity: f32,
+    pub volume: f32,
+    pub icon: NotificationIcon,
+    pub use_base64_icon: bool,
+    // Notifications are grouped by this name in the history of XSOverlay.
+    pub source_app: String,
+}
+
+impl Default for MessageDefaults {
+    fn default() -> Self {
+        MessageDefaults {
+            timeout: 1.5,
+            height: 175f32,
+            opacity: 1.0,
+            volume: 0.7,
+            icon: NotificationIcon::Default,
+            use_base64_icon: false,
+            source_app: "xsoverlay_vrc_notifier".to_owned(),
+        }
+    }
 }
 
 pub struct MessageObjectBuilder {
     source: MessageObject,
 }
 
+// Modifies a message which has already been built.
+impl From<MessageObject> for MessageObjectBuilder {
+    fn from(source: MessageObject) -> Self {
+        MessageObjectBuilder { source }
+    }
+}
+
 #[allow(dead_code)]
 impl MessageObjectBuilder {
     pub fn new(title: String) -> MessageObjectBuilder {
+        Self::with_defaults(title, &MessageDefaults::default())
+    }
+
+    pub fn with_defaults(title: String, defaults: &MessageDefaults) -> MessageObjectBuilder {
         MessageObjectBuilder {
             source: MessageObject {
                 message_type: MessageType::NotificationPopup,
                 index: 0,
-                timeout: 1.5,
-                height: 175f32,
-                opacity: 1.0,
-                volume: 0.7,
+                timeout: defaults.timeout,
+                height: defaults.height,
+                opacity: defaults.opacity,
+                volume: defaults.volume,
                 audio_path: NotificationAudio::Default,
                 title: title,
                 content: "".to_owned(),
-                use_base64_icon: false,
-                icon: NotificationIcon::Default,
-                source_app: "xsoverlay_vrc_notifier".to_owned(),
+                use_base64_icon: defaults.use_base64_icon,
+                icon: defaults.icon.clone(),
+                source_app: defaults.source_app.clone(),
             },
         }
     }
 
+    // Shows what is playing, e.g. the title of the video and who requested it, on the media player.
+    pub fn new_media_player(title: String, source: String) -> MessageObjectBuilder {
+        Self::new(title)
+            .set_message_type(MessageType::MediaPlayerInformation)
+            .set_content(source)
+    }
+
     pub fn build(self) -> MessageObject {
         MessageObject { ..self.source }
     }
 
+    // Builds the message only if XSOverlay can show it, e.g. not with a NaN timeout.
+    pub fn try_build(self) -> Result<MessageObject, BuildError> {
+        let message = self.build();
+        let is_valid = |value: f32| value.is_finite() && value >= 0.0;
+        if !is_valid(message.timeout) {
+            return Err(BuildError::InvalidTimeout(message.timeout));
+        }
+        if !is_valid(message.height) {
+            return Err(BuildError::InvalidHeight(message.height));
+        }
+        if !(0.0..=1.0).contains(&message.opacity) {
+            return Err(BuildError::InvalidOpacity(message.opacity));
+        }
+        if !(0.0..=1.0).contains(&message.volume) {
+            return Err(BuildError::InvalidVolume(message.volume));
+        }
+        if message.title.is_empty() {
+            return Err(BuildError::EmptyTitle);
+        }
+        Ok(message)
+    }
+
+    pub fn set_message_type(mut self, message_type: MessageType) -> Self {
+        self.source.message_type = message_type;
+        self
+    }
+
     pub fn set_content(mut self, content: String) -> Self {
         self.source.content = content;
         self
     }
 
+    // Appends the text to the content, separated by a space.
+    pub fn append_content(mut self, text: &str) -> Self {
+        if !self.source.content.is_empty() {
+            self.source.content.push(' ');
+        }
+        self.source.content.push_str(text);
+        self
+    }
+
     pub fn set_audio(mut self, audio: NotificationAudio) -> Self {
         self.source.audio_path = audio;
         self
@@ -78,13 +187,70 @@ impl MessageObjectBuilder {
         self
     }
 
+    pub fn set_height(mut self, height: f32) -> Self {
+        self.source.height = height;
+        self
+    }
+
     pub fn set_timeout(mut self, timeout: f32) -> Self {
         self.source.timeout = timeout;
         self
     }
+
+    // Notifications with different indices are stacked instead of replacing each other.
+    pub fn set_index(mut self, index: i32) -> Self {
+        self.source.index = index;
+        self
+    }
+
+    // Notifications are grouped by this name in the history of XSOverlay.
+    pub fn set_source_app(mut self, source_app: String) -> Self {
+        self.source.source_app = source_app;
+        self
+    }
+}
+
+// The value of a message which XSOverlay can't show.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildError {
+    // The timeout in seconds, which must be finite and not negative.
+    InvalidTimeout(f32),
+    // The height in pixels, which must be finite and not negative.
+    InvalidHeight(f32),
+    // The opacity, which must be from 0 to 1.
+    InvalidOpacity(f32),
+    // The volume, which must be from 0 to 1.
+    InvalidVolume(f32),
+    EmptyTitle,
 }
 
-#[derive(Serialize_repr, Debug)]
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::InvalidTimeout(timeout) => write!(
+                f,
+                "invalid timeout: {} (must be finite and not negative)",
+                timeout
+            ),
+            BuildError::InvalidHeight(height) => write!(
+                f,
+                "invalid height: {} (must be finite and not negative)",
+                height
+            ),
+            BuildError::InvalidOpacity(opacity) => {
+                write!(f, "invalid opacity: {} (must be from 0 to 1)", opacity)
+            }
+            BuildError::InvalidVolume(volume) => {
+                write!(f, "invalid volume: {} (must be from 0 to 1)", volume)
+            }
+            BuildError::EmptyTitle => write!(f, "the title is empty"),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+#[derive(Serialize_repr, Debug, Clone, Copy)]
 #[repr(u8)]
 #[allow(dead_code)]
 pub enum MessageType {
@@ -92,9 +258,10 @@ pub enum MessageType {
     MediaPlayerInformation = 2,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, Default)]
 #[allow(dead_code)]
 pub enum NotificationType {
+    #[default]
     Default,
     Error,
     Warning,
@@ -116,35 +283,323 @@ where
     })
 }
 
-pub struct NotificationClient {
-    socket: UdpSocket,
+// Where the datagrams are written to, which is replaced with a fake in tests.
+pub trait DatagramSink {
+    fn send_to(&self, buf: &[u8], addr: &str) -> io::Result<usize>;
+}
+
+impl DatagramSink for UdpSocket {
+    fn send_to(&self, buf: &[u8], addr: &str) -> io::Result<usize> {
+        UdpSocket::send_to(self, buf, addr)
+    }
+}
+
+// The largest payload of a UDP datagram over IPv4.
+pub const DEFAULT_MAX_PAYLOAD_SIZE: usize = 65507;
+
+pub const DEFAULT_PORT: u16 = 42069;
+
+// Chooses the port of XSOverlay: the one given by XSOVERLAY_PORT if it is valid, otherwise the default one.
+// XSOverlay receives notifications over UDP, which can't be probed for a listener,
+// and its WebSocket API on 42070 accepts TCP but not the datagrams, so no port is probed.
+pub fn discover_port(env_port: Option<&str>) -> u16 {
+    env_port
+        .and_then(|port| port.trim().parse().ok())
+        // 0 is not a port to send to, as --xsoverlay-port rejects it.
+        .filter(|&port| port != 0)
+        .unwrap_or(DEFAULT_PORT)
+}
+
+// Reads XSOVERLAY_PORT from the environment of this process.
+pub fn discover_env_port() -> u16 {
+    discover_port(std::env::var("XSOVERLAY_PORT").ok().as_deref())
+}
+
+pub struct NotificationClient<D: DatagramSink = UdpSocket> {
+    socket: D,
     endpoint: String,
+    retry_policy: RetryPolicy,
+    max_payload_size: usize,
 }
 
 impl NotificationClient {
     pub fn new() -> Result<NotificationClient, io::Error> {
-        Self::new_with_endpoint("127.0.0.1", 42069)
+        Self::new_with_endpoint("127.0.0.1", discover_env_port())
+    }
+
+    pub fn new_with_endpoint(host: &str, port: u16) -> Result<NotificationClient, io::Error> {
+        Self::new_with_bind("127.0.0.1:0", host, port)
+    }
+
+    // Binds to the address, e.g. `0.0.0.0:0`, so that XSOverlay on another machine is reachable.
+    pub fn new_with_bind(
+        bind: &str,
+        host: &str,
+        port: u16,
+    ) -> Result<NotificationClient, io::Error> {
+        let socket = UdpSocket::bind(bind)?;
+        Ok(NotificationClient::new_with_sink(socket, host, port))
     }
+}
 
-    pub fn new_with_endpoint(host: &str, port: i32) -> Result<NotificationClient, io::Error> {
-        let socket = UdpSocket::bind("127.0.0.1:0")?;
-        Ok(NotificationClient {
-            socket: socket,
+impl<D: DatagramSink> NotificationClient<D> {
+    pub fn new_with_sink(socket: D, host: &str, port: u16) -> NotificationClient<D> {
+        NotificationClient {
+            socket,
             endpoint: format!("{}:{}", host, port),
-        })
+            retry_policy: RetryPolicy::default(),
+            max_payload_size: DEFAULT_MAX_PAYLOAD_SIZE,
+        }
+    }
+
+    pub fn set_max_payload_size(&mut self, max_payload_size: usize) {
+        self.max_payload_size = max_payload_size;
+    }
+
+    pub fn send_message_with_retry(
+        &self,
+        message: &MessageObject,
+        policy: &RetryPolicy,
+    ) -> Result<(), SendMessageError> {
+        let json = self.to_payload(message)?;
+        retry_with_backoff(policy, || {
+            self.socket.send_to(json.as_bytes(), &self.endpoint)
+        })?;
+        Ok(())
     }
 
-    pub fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+    // A base64 icon too large to send is replaced with the default one.
+    fn to_payload(&self, message: &MessageObject) -> Result<String, SendMessageError> {
         let json = message.to_json()?;
-        self.socket.send_to(json.as_bytes(), &self.endpoint)?;
+        if json.len() <= self.max_payload_size {
+            return Ok(json);
+        }
+        if message.use_base64_icon {
+            let fallback = MessageObjectBuilder::from(message.clone())
+                .set_icon(NotificationIcon::Default, false)
+                .build();
+            let json = fallback.to_json()?;
+            if json.len() <= self.max_payload_size {
+                return Ok(json);
+            }
+        }
+        Err(SendMessageError::PayloadTooLarge(json.len()))
+    }
+}
+
+pub trait NotificationSink {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError>;
+
+    // Sends the messages held back by the sink, if any.
+    fn flush(&self) -> Result<(), SendMessageError> {
         Ok(())
     }
 }
 
+// Shares a sink, e.g. the overlay client, with what else notifies through it.
+impl<T: NotificationSink + ?Sized> NotificationSink for Rc<T> {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        (**self).send_message(message)
+    }
+
+    fn flush(&self) -> Result<(), SendMessageError> {
+        (**self).flush()
+    }
+}
+
+// Sends every message to all of the sinks, returning the first error after trying them all.
+impl NotificationSink for Vec<Box<dyn NotificationSink>> {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        let mut result = Ok(());
+        for sink in self {
+            let r = sink.send_message(message);
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        result
+    }
+
+    fn flush(&self) -> Result<(), SendMessageError> {
+        let mut result = Ok(());
+        for sink in self {
+            let r = sink.flush();
+            if result.is_ok() {
+                result = r;
+            }
+        }
+        result
+    }
+}
+
+impl<D: DatagramSink> NotificationSink for NotificationClient<D> {
+    fn send_message(&self, message: &MessageObject) -> Result<(), SendMessageError> {
+        self.send_message_with_retry(message, &self.retry_policy)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // The number of attempts including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_millis(400),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Returns the wait before the given retry, doubling from initial_backoff.
+    fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .checked_mul(1 << retry.min(16))
+            .unwrap_or(self.max_backoff);
+        backoff.min(self.max_backoff)
+    }
+}
+
+fn is_retryable(kind: io::ErrorKind) -> bool {
+    matches!(
+        kind,
+        io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted | io::ErrorKind::TimedOut
+    )
+}
+
+fn retry_with_backoff<T, F>(policy: &RetryPolicy, mut op: F) -> io::Result<T>
+where
+    F: FnMut() -> io::Result<T>,
+{
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if attempt < policy.max_attempts && is_retryable(e.kind()) => {
+                std::thread::sleep(policy.backoff(attempt - 1));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Sending a datagram succeeds even if nobody is listening,
+// so the reachability of XSOverlay has to be checked separately.
+pub trait LivenessProbe {
+    fn is_alive(&self) -> bool;
+}
+
+// The port of the WebSocket API of XSOverlay, which accepts TCP unlike the notification port.
+pub const WEBSOCKET_API_PORT: u16 = 42070;
+
+// Checks whether XSOverlay accepts a connection on its WebSocket API port.
+pub struct TcpLivenessProbe {
+    host: String,
+    timeout: Duration,
+}
+
+impl TcpLivenessProbe {
+    pub fn new() -> TcpLivenessProbe {
+        Self::new_with_host("127.0.0.1")
+    }
+
+    // Probes XSOverlay on the host which the notifications are sent to, e.g. on another machine.
+    pub fn new_with_host(host: &str) -> TcpLivenessProbe {
+        TcpLivenessProbe {
+            host: host.to_owned(),
+            timeout: Duration::from_millis(200),
+        }
+    }
+}
+
+impl Default for TcpLivenessProbe {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LivenessProbe for TcpLivenessProbe {
+    // A host which can't be resolved is reported as down.
+    fn is_alive(&self) -> bool {
+        (self.host.as_str(), WEBSOCKET_API_PORT)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .is_some_and(|addr| TcpStream::connect_timeout(&addr, self.timeout).is_ok())
+    }
+}
+
+// How long the result of a probe is reused.
+pub const DEFAULT_LIVENESS_TTL: Duration = Duration::from_secs(5);
+
+// Reuses the result of the probe for a while, so that a connection is not attempted on every message.
+pub struct CachedLivenessProbe<P: LivenessProbe, C: CurrentTimeProvider> {
+    probe: P,
+    clock: C,
+    ttl: chrono::Duration,
+    last: Cell<Option<(DateTime<Utc>, bool)>>,
+}
+
+impl<P: LivenessProbe, C: CurrentTimeProvider> CachedLivenessProbe<P, C> {
+    pub fn new(probe: P, clock: C, ttl: Duration) -> Self {
+        CachedLivenessProbe {
+            probe,
+            clock,
+            ttl: chrono::Duration::from_std(ttl).unwrap(),
+            last: Cell::new(None),
+        }
+    }
+}
+
+impl<P: LivenessProbe, C: CurrentTimeProvider> LivenessProbe for CachedLivenessProbe<P, C> {
+    fn is_alive(&self) -> bool {
+        let now = self.clock.current_time();
+        if let Some((probed_at, is_alive)) = self.last.get() {
+            if now - probed_at < self.ttl {
+                return is_alive;
+            }
+        }
+        let is_alive = self.probe.is_alive();
+        self.last.set(Some((now, is_alive)));
+        is_alive
+    }
+}
+
 #[derive(Debug)]
 pub enum SendMessageError {
     JsonError(serde_json::Error),
     SendError(io::Error),
+    // The size of the serialized message, which exceeds the limit of the datagram.
+    PayloadTooLarge(usize),
+}
+
+impl fmt::Display for SendMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendMessageError::JsonError(e) => write!(f, "failed to serialize message: {}", e),
+            SendMessageError::SendError(e) => write!(f, "failed to send message: {}", e),
+            SendMessageError::PayloadTooLarge(size) => {
+                write!(f, "message is too large to send: {} bytes", size)
+            }
+        }
+    }
+}
+
+impl Error for SendMessageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SendMessageError::JsonError(e) => Some(e),
+            SendMessageError::SendError(e) => Some(e),
+            SendMessageError::PayloadTooLarge(_) => None,
+        }
+    }
 }
 
 impl From<serde_json::Error> for SendMessageError {
@@ -158,3 +613,357 @@ impl From<io::Error> for SendMessageError {
         SendMessageError::SendError(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    use crate::testing::MockTimeProvider;
+
+    #[test]
+    fn try_build_rejects_nan_timeout() {
+        let result = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_timeout(f32::NAN)
+            .try_build();
+        match result {
+            Err(BuildError::InvalidTimeout(timeout)) => assert!(timeout.is_nan()),
+            _ => panic!("the NaN timeout was accepted"),
+        }
+    }
+
+    #[test]
+    fn try_build_rejects_negative_height() {
+        let result = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_height(-1.0)
+            .try_build();
+        assert_eq!(Some(BuildError::InvalidHeight(-1.0)), result.err());
+        assert_eq!(
+            "invalid height: -1 (must be finite and not negative)",
+            BuildError::InvalidHeight(-1.0).to_string()
+        );
+    }
+
+    #[test]
+    fn try_build_accepts_valid_message() {
+        let message = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_content("Hello".to_owned())
+            .set_timeout(2.0)
+            .set_height(200.0)
+            .try_build()
+            .unwrap();
+        assert_eq!("Alice joined.", message.title());
+        assert_eq!(2.0, message.timeout());
+        assert_eq!(200.0, message.height());
+    }
+
+    struct CountingProbe {
+        is_alive: Cell<bool>,
+        probes: Cell<u32>,
+    }
+
+    impl LivenessProbe for CountingProbe {
+        fn is_alive(&self) -> bool {
+            self.probes.set(self.probes.get() + 1);
+            self.is_alive.get()
+        }
+    }
+
+    #[test]
+    fn cached_probe_reuses_result_until_it_expires() {
+        let clock = MockTimeProvider::new();
+        let probe = CountingProbe {
+            is_alive: Cell::new(true),
+            probes: Cell::new(0),
+        };
+        let cached = CachedLivenessProbe::new(probe, clock.clone(), Duration::from_secs(5));
+        assert!(cached.is_alive());
+        cached.probe.is_alive.set(false);
+        clock.advance(chrono::Duration::seconds(4));
+        assert!(cached.is_alive());
+        assert_eq!(1, cached.probe.probes.get());
+
+        clock.advance(chrono::Duration::seconds(1));
+        assert!(!cached.is_alive());
+        assert!(!cached.is_alive());
+        assert_eq!(2, cached.probe.probes.get());
+    }
+
+    #[test]
+    fn discover_port_prefers_env_var() {
+        assert_eq!(42100, discover_port(Some("42100")));
+        assert_eq!(42100, discover_port(Some(" 42100\n")));
+    }
+
+    #[test]
+    fn discover_port_falls_back_to_default_port() {
+        assert_eq!(DEFAULT_PORT, discover_port(None));
+        // an invalid value is ignored, rather than the WebSocket API port being chosen.
+        assert_eq!(DEFAULT_PORT, discover_port(Some("xsoverlay")));
+        assert_eq!(DEFAULT_PORT, discover_port(Some("65536")));
+        assert_eq!(DEFAULT_PORT, discover_port(Some("0")));
+    }
+
+    struct FlakySocket {
+        failures: Cell<u32>,
+        kind: io::ErrorKind,
+        sent: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl FlakySocket {
+        fn new(failures: u32, kind: io::ErrorKind) -> Self {
+            FlakySocket {
+                failures: Cell::new(failures),
+                kind,
+                sent: RefCell::new(Vec::new()),
+            }
+        }
+
+        fn send_to(&self, buf: &[u8]) -> io::Result<usize> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(io::Error::from(self.kind));
+            }
+            self.sent.borrow_mut().push(buf.to_vec());
+            Ok(buf.len())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSocket {
+        sent: RefCell<Vec<(Vec<u8>, String)>>,
+    }
+
+    impl DatagramSink for RecordingSocket {
+        fn send_to(&self, buf: &[u8], addr: &str) -> io::Result<usize> {
+            self.sent.borrow_mut().push((buf.to_vec(), addr.to_owned()));
+            Ok(buf.len())
+        }
+    }
+
+    fn immediate_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn retry_delivers_message_after_transient_errors() {
+        let socket = FlakySocket::new(2, io::ErrorKind::WouldBlock);
+        let json = MessageObjectBuilder::new("test".to_owned())
+            .build()
+            .to_json()
+            .unwrap();
+        let result = retry_with_backoff(&immediate_policy(3), || socket.send_to(json.as_bytes()));
+        assert_eq!(json.len(), result.unwrap());
+        assert_eq!(vec![json.into_bytes()], *socket.sent.borrow());
+    }
+
+    #[test]
+    fn retry_gives_up_after_max_attempts() {
+        let socket = FlakySocket::new(3, io::ErrorKind::Interrupted);
+        let result = retry_with_backoff(&immediate_policy(3), || socket.send_to(b"test"));
+        assert_eq!(io::ErrorKind::Interrupted, result.unwrap_err().kind());
+        assert!(socket.sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn retry_does_not_retry_fatal_errors() {
+        let socket = FlakySocket::new(1, io::ErrorKind::PermissionDenied);
+        let result = retry_with_backoff(&immediate_policy(3), || socket.send_to(b"test"));
+        assert_eq!(io::ErrorKind::PermissionDenied, result.unwrap_err().kind());
+        assert_eq!(0, socket.failures.get());
+        assert!(socket.sent.borrow().is_empty());
+    }
+
+    #[test]
+    fn send_message_error_displays_inner_error() {
+        let json_error = serde_json::from_str::<i32>("").unwrap_err();
+        let json_error_text = json_error.to_string();
+        let error = SendMessageError::from(json_error);
+        assert_eq!(
+            format!("failed to serialize message: {}", json_error_text),
+            error.to_string()
+        );
+        assert_eq!(json_error_text, error.source().unwrap().to_string());
+
+        let error = SendMessageError::from(io::Error::other("unreachable"));
+        assert_eq!("failed to send message: unreachable", error.to_string());
+        let source = error.source().unwrap();
+        assert_eq!(
+            io::ErrorKind::Other,
+            source.downcast_ref::<io::Error>().unwrap().kind()
+        );
+    }
+
+    #[test]
+    fn retry_policy_backoff_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_millis(150),
+        };
+        assert_eq!(Duration::from_millis(50), policy.backoff(0));
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(150), policy.backoff(2));
+        assert_eq!(Duration::from_millis(150), policy.backoff(40));
+    }
+
+    #[test]
+    fn message_object_serializes_source_app() {
+        let default: serde_json::Value = serde_json::from_str(
+            &MessageObjectBuilder::new("test".to_owned())
+                .build()
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("xsoverlay_vrc_notifier", default["sourceApp"]);
+
+        let overridden: serde_json::Value = serde_json::from_str(
+            &MessageObjectBuilder::new("test".to_owned())
+                .set_source_app("vrc_doorkeeper-alt".to_owned())
+                .build()
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!("vrc_doorkeeper-alt", overridden["sourceApp"]);
+    }
+
+    #[test]
+    fn message_object_starts_with_defaults() {
+        let defaults = MessageDefaults {
+            timeout: 4.0,
+            height: 200f32,
+            opacity: 0.5,
+            volume: 0.2,
+            icon: NotificationIcon::Warning,
+            use_base64_icon: false,
+            source_app: "vrc_doorkeeper-alt".to_owned(),
+        };
+        let json: serde_json::Value = serde_json::from_str(
+            &MessageObjectBuilder::with_defaults("test".to_owned(), &defaults)
+                .build()
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(4.0, json["timeout"]);
+        assert_eq!(200.0, json["height"]);
+        assert_eq!(0.5, json["opacity"]);
+        assert_eq!(0.2, json["volume"]);
+        assert_eq!("warning", json["icon"]);
+        assert_eq!("vrc_doorkeeper-alt", json["sourceApp"]);
+
+        // the builder overrides the defaults.
+        let message = MessageObjectBuilder::with_defaults("test".to_owned(), &defaults)
+            .set_timeout(1f32)
+            .build();
+        assert_eq!(1f32, message.timeout());
+    }
+
+    #[test]
+    fn message_object_serializes_message_type() {
+        let popup: serde_json::Value = serde_json::from_str(
+            &MessageObjectBuilder::new("test".to_owned())
+                .build()
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(1, popup["messageType"]);
+
+        let media: serde_json::Value = serde_json::from_str(
+            &MessageObjectBuilder::new_media_player(
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_owned(),
+                "paralleltree".to_owned(),
+            )
+            .build()
+            .to_json()
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(2, media["messageType"]);
+        assert_eq!("paralleltree", media["content"]);
+    }
+
+    #[test]
+    fn client_bound_to_custom_address_sends_to_endpoint() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        let client = NotificationClient::new_with_bind("0.0.0.0:0", "127.0.0.1", port).unwrap();
+        assert!(client.socket.local_addr().unwrap().ip().is_unspecified());
+
+        let message = MessageObjectBuilder::new("test".to_owned()).build();
+        client.send_message(&message).unwrap();
+        let mut buf = [0u8; 1024];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        assert_eq!(message.to_json().unwrap().as_bytes(), &buf[..len]);
+    }
+
+    #[test]
+    fn client_formats_endpoint_for_boundary_ports() {
+        let client = NotificationClient::new_with_endpoint("127.0.0.1", 0).unwrap();
+        assert_eq!("127.0.0.1:0", client.endpoint);
+        let client = NotificationClient::new_with_endpoint("127.0.0.1", 65535).unwrap();
+        assert_eq!("127.0.0.1:65535", client.endpoint);
+    }
+
+    #[test]
+    fn client_sends_serialized_message_to_datagram_sink() {
+        let client =
+            NotificationClient::new_with_sink(RecordingSocket::default(), "127.0.0.1", 42069);
+        let message = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_timeout(1f32)
+            .build();
+        client.send_message(&message).unwrap();
+        let expected = concat!(
+            r#"{"messageType":1,"index":0,"timeout":1.0,"height":175.0,"opacity":1.0,"volume":0.7,"#,
+            r#""audioPath":"default","title":"Alice joined.","content":"","useBase64Icon":false,"#,
+            r#""icon":"default","sourceApp":"xsoverlay_vrc_notifier"}"#
+        );
+        assert_eq!(
+            vec![(expected.as_bytes().to_vec(), "127.0.0.1:42069".to_owned())],
+            *client.socket.sent.borrow()
+        );
+    }
+
+    #[test]
+    fn client_falls_back_to_default_icon_for_oversized_payload() {
+        let mut client =
+            NotificationClient::new_with_sink(RecordingSocket::default(), "127.0.0.1", 42069);
+        client.set_max_payload_size(1024);
+        let message = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_icon(NotificationIcon::Custom("A".repeat(2048)), true)
+            .build();
+        client.send_message(&message).unwrap();
+
+        let sent = client.socket.sent.borrow();
+        let json: serde_json::Value = serde_json::from_slice(&sent[0].0).unwrap();
+        assert_eq!("default", json["icon"]);
+        assert_eq!(false, json["useBase64Icon"]);
+        assert_eq!("Alice joined.", json["title"]);
+    }
+
+    #[test]
+    fn client_rejects_oversized_payload() {
+        let mut client =
+            NotificationClient::new_with_sink(RecordingSocket::default(), "127.0.0.1", 42069);
+        client.set_max_payload_size(1024);
+        let message = MessageObjectBuilder::new("Alice joined.".to_owned())
+            .set_content("A".repeat(2048))
+            .build();
+        let expected = message.to_json().unwrap().len();
+        match client.send_message(&message) {
+            Err(SendMessageError::PayloadTooLarge(size)) => assert_eq!(expected, size),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert!(client.socket.sent.borrow().is_empty());
+    }
+}
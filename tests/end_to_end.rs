@@ -0,0 +1,226 @@
+// Runs the log processor and the notifier together over log files written into a temporary directory.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use tempfile::TempDir;
+
+use vrc_doorkeeper::notifier::{NotifierSettings, VrcToXsOverlayNotifier};
+use vrc_doorkeeper::reader::VrChatLogProcessor;
+// The fixtures of the unit tests refer to these as `crate::notifier` and so on.
+use vrc_doorkeeper::{notifier, reader, vrc, xsoverlay};
+
+#[path = "../src/testing.rs"]
+#[allow(dead_code)]
+mod testing;
+
+use testing::{MockTimeProvider, RecordingSink};
+
+type Notifier = VrcToXsOverlayNotifier<MockTimeProvider, RecordingSink>;
+
+struct Harness {
+    dir: TempDir,
+    clock: MockTimeProvider,
+    sink: RecordingSink,
+    processor: VrChatLogProcessor<Notifier>,
+}
+
+impl Harness {
+    fn new(settings: NotifierSettings) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let clock = MockTimeProvider::new();
+        let sink = RecordingSink::default();
+        let notifier = VrcToXsOverlayNotifier::new(sink.clone(), clock.clone(), settings);
+        let processor = VrChatLogProcessor::new(dir.path().to_path_buf(), notifier);
+        Harness {
+            dir,
+            clock,
+            sink,
+            processor,
+        }
+    }
+
+    fn log_path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    // Writes the entries as VRChat does, each followed by a blank line.
+    fn append(&self, name: &str, entries: &[(&str, &str)]) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(name))
+            .unwrap();
+        for (time, body) in entries {
+            write!(file, "2021.12.01 {} Log        -  {}\n\n", time, body).unwrap();
+        }
+    }
+
+    // Makes the log look older than any written later, as the modified times may tie.
+    fn age(&self, name: &str) {
+        File::options()
+            .write(true)
+            .open(self.log_path(name))
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60))
+            .unwrap();
+    }
+
+    fn poll(&mut self) {
+        self.processor.process_log().unwrap();
+    }
+
+    fn advance(&self, seconds: i64) {
+        self.clock.advance(chrono::Duration::seconds(seconds));
+    }
+
+    fn titles(&self) -> Vec<String> {
+        self.sink.titles()
+    }
+}
+
+fn settings() -> NotifierSettings {
+    NotifierSettings {
+        notify_authenticated: true,
+        notify_instance_full: true,
+        ..Default::default()
+    }
+}
+
+const LOG: &str = "output_log_2021-12-01_23-00-00.txt";
+const ROTATED_LOG: &str = "output_log_2021-12-01_23-30-00.txt";
+const LOGGED_IN: &str = "Logged in as paralleltree.";
+
+#[test]
+fn notifies_live_events_of_session() {
+    let mut harness = Harness::new(settings());
+    harness.append(
+        LOG,
+        &[
+            ("23:00:05", "[Behaviour] User Authenticated: paralleltree"),
+            ("23:01:00", "[Behaviour] Entering Room: The Great Pug"),
+            ("23:01:10", "[Behaviour] Finished entering world."),
+            ("23:01:11", "[Behaviour] OnPlayerJoined paralleltree"),
+            ("23:01:11", "[Behaviour] OnPlayerJoined Alice"),
+        ],
+    );
+    harness.poll();
+    // the lines written before reading started only tell who is there, but for the login.
+    assert_eq!(vec![LOGGED_IN], harness.titles());
+
+    harness.advance(60);
+    harness.append(
+        LOG,
+        &[
+            ("23:02:11", "[Behaviour] OnPlayerJoined Bob"),
+            ("23:02:30", "[Behaviour] OnPlayerLeft Alice"),
+        ],
+    );
+    harness.poll();
+    assert_eq!(
+        vec![LOGGED_IN, "Bob joined.", "Alice left."],
+        harness.titles()
+    );
+
+    // the players already in the next world are not notified while it loads.
+    harness.advance(60);
+    harness.append(
+        LOG,
+        &[
+            ("23:03:30", "[Behaviour] OnLeftRoom"),
+            ("23:03:31", "[Behaviour] Entering Room: Another World"),
+            ("23:03:40", "[Behaviour] Finished entering world."),
+            ("23:03:41", "[Behaviour] OnPlayerJoined Carol"),
+        ],
+    );
+    harness.poll();
+    harness.advance(10);
+    harness.append(
+        LOG,
+        &[
+            ("23:03:51", "[Behaviour] OnPlayerJoined Dave"),
+            ("23:03:52", "[Behaviour] Instance is full"),
+        ],
+    );
+    harness.poll();
+    assert_eq!(
+        vec![
+            LOGGED_IN,
+            "Bob joined.",
+            "Alice left.",
+            "Dave joined.",
+            "The instance is full.",
+        ],
+        harness.titles()
+    );
+    let mut names: Vec<_> = harness
+        .processor
+        .processor_mut()
+        .current_roster()
+        .names()
+        .map(str::to_owned)
+        .collect();
+    names.sort();
+    assert_eq!(vec!["Carol", "Dave"], names);
+}
+
+#[test]
+fn follows_log_rotated_mid_session() {
+    let mut harness = Harness::new(settings());
+    harness.append(
+        LOG,
+        &[
+            ("23:00:05", "[Behaviour] User Authenticated: paralleltree"),
+            ("23:01:10", "[Behaviour] Finished entering world."),
+            ("23:01:11", "[Behaviour] OnPlayerJoined Alice"),
+        ],
+    );
+    harness.poll();
+    harness.advance(60);
+    harness.append(LOG, &[("23:02:11", "[Behaviour] OnPlayerJoined Bob")]);
+    harness.poll();
+    assert_eq!(vec![LOGGED_IN, "Bob joined."], harness.titles());
+
+    // VRChat is restarted, which starts a new log.
+    harness.append(
+        LOG,
+        &[("23:29:00", "VRCApplication: OnApplicationQuit at 1800")],
+    );
+    harness.age(LOG);
+    harness.append(
+        ROTATED_LOG,
+        &[
+            ("23:30:05", "[Behaviour] User Authenticated: paralleltree"),
+            ("23:31:10", "[Behaviour] Finished entering world."),
+            ("23:31:11", "[Behaviour] OnPlayerJoined Carol"),
+        ],
+    );
+    harness.poll();
+    // the new log is read from the start as on a fresh start.
+    assert_eq!(vec![LOGGED_IN, "Bob joined.", LOGGED_IN], harness.titles());
+
+    harness.advance(60);
+    harness.append(
+        ROTATED_LOG,
+        &[
+            ("23:32:11", "[Behaviour] OnPlayerJoined Dave"),
+            ("23:32:30", "[Behaviour] OnPlayerLeft Carol"),
+        ],
+    );
+    // the previous log is no longer read.
+    harness.append(LOG, &[("23:32:40", "[Behaviour] OnPlayerJoined Mallory")]);
+    harness.age(LOG);
+    harness.poll();
+    assert_eq!(
+        vec![
+            LOGGED_IN,
+            "Bob joined.",
+            LOGGED_IN,
+            "Dave joined.",
+            "Carol left.",
+        ],
+        harness.titles()
+    );
+}